@@ -0,0 +1,119 @@
+//! Hot paths of the engine, run with `cargo bench`
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use rand::{rngs::StdRng, SeedableRng};
+use std::hint::black_box;
+use tetris_engine::{
+    engine::{GameEngine, Rules, EMPTY, GARBAGE},
+    tetris_block::{TetrisBlock, SHAPES},
+};
+
+/// Lines along gravity of the tall board, far more than any terminal fits
+const TALL: usize = 400;
+
+fn engine(width: usize, height: usize) -> GameEngine {
+    let rules = Rules {
+        random_rotation: false,
+        ..Rules::default()
+    };
+    GameEngine::new(rules, width, height, 1)
+}
+
+fn block(shape: usize) -> TetrisBlock {
+    TetrisBlock::from_shape(shape, &mut StdRng::seed_from_u64(0), false)
+}
+
+/// Board with every other cell of its far half filled, the way a messy stack looks
+fn checkered(width: usize, height: usize) -> GameEngine {
+    let mut engine = engine(width, height);
+    for (x, col) in engine.filled_area.iter_mut().enumerate().skip(width / 2) {
+        for (y, cell) in col.iter_mut().enumerate() {
+            if (x + y) % 2 == 0 {
+                *cell = Some(GARBAGE);
+            }
+        }
+    }
+    engine
+}
+
+fn end_move_pos(c: &mut Criterion) {
+    let mut engine = engine(TALL, 10);
+    // upright I piece falls the whole board down an empty lane
+    engine.current_block = block(0);
+    engine.current_block.pattern = TetrisBlock::rotate90(&engine.current_block.pattern);
+    engine.current_block.pos = (0, 0);
+    c.bench_function("get_end_move_pos tall board", |b| {
+        b.iter(|| black_box(&engine).get_end_move_pos())
+    });
+}
+
+fn finish_round(c: &mut Criterion) {
+    // four lines missing their first cell, filled by an upright I piece for a tetris
+    let setup = || {
+        let mut engine = engine(40, 10);
+        for col in &mut engine.filled_area[36..] {
+            col.iter_mut()
+                .skip(1)
+                .for_each(|cell| *cell = Some(GARBAGE));
+        }
+        engine.current_block = block(0);
+        engine.current_block.pattern = TetrisBlock::rotate90(&engine.current_block.pattern);
+        engine.current_block.pos = (36, 0);
+        engine
+    };
+    c.bench_function("finish_round tetris", |b| {
+        b.iter_batched(
+            setup,
+            |mut engine| engine.finish_round(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn rotate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rotate90");
+    for shape in 0..SHAPES {
+        let mut engine = engine(40, 10);
+        engine.current_block = block(shape);
+        engine.current_block.pos = (10, 4);
+        group.bench_function(format!("{}", engine.current_block.kind), |b| {
+            b.iter(|| engine.rotate90())
+        });
+    }
+    group.finish();
+}
+
+fn board_scan(c: &mut Criterion) {
+    let engine = checkered(TALL, 10);
+    // stands in for the painter, collecting each filled cell the way the board canvas paints it
+    c.bench_function("board scan", |b| {
+        b.iter(|| {
+            let mut painted = Vec::with_capacity(TALL * 10);
+            for (x, col) in black_box(&engine).filled_area.iter().enumerate() {
+                for (y, cell) in col.iter().enumerate() {
+                    if *cell != EMPTY {
+                        painted.push((x, y, cell.unwrap(), engine.placed[x][y]));
+                    }
+                }
+            }
+            painted
+        })
+    });
+}
+
+fn analysis(c: &mut Criterion) {
+    let engine = checkered(TALL, 10);
+    c.bench_function("height_profile and holes", |b| {
+        b.iter(|| (engine.height_profile(), engine.holes()))
+    });
+}
+
+criterion_group!(
+    benches,
+    end_move_pos,
+    finish_round,
+    rotate,
+    board_scan,
+    analysis
+);
+criterion_main!(benches);
@@ -0,0 +1,53 @@
+//! Plays a scripted game without a terminal, the way a bot or test would drive the engine.
+//!
+//! Run with `cargo run --example headless`
+
+use tetris_engine::engine::{Action, GameEngine, GameState, Rules, EMPTY};
+
+/// Inputs of each piece: `r` rotates, `u` and `d` move it along the side axis, `s` soft drops,
+/// `.` lets gravity move it, `h` holds and ` ` drops it
+const SCRIPT: [&str; 8] = [
+    "dddddd ", "ddd ", "r.s ", "uu ", "uuuuuu ", "rdddd ", "h.ss ", "ruuuu ",
+];
+
+fn main() {
+    let rules = Rules {
+        random_rotation: false,
+        ..Rules::default()
+    };
+    let mut engine = GameEngine::new(rules, 20, 10, 57);
+
+    let mut locks = 0;
+    for round in 0..50 {
+        for input in SCRIPT[round % SCRIPT.len()].chars() {
+            let result = match input {
+                'r' => engine.apply(Action::Rotate),
+                'u' => engine.apply(Action::MoveUp),
+                'd' => engine.apply(Action::MoveDown),
+                's' => engine.apply(Action::SoftDrop),
+                'h' => engine.apply(Action::Hold),
+                ' ' => engine.apply(Action::HardDrop),
+                _ => engine.tick(),
+            };
+            locks += usize::from(result.is_some());
+        }
+        if engine.game_state == GameState::Finished {
+            break;
+        }
+    }
+
+    for line in &engine.filled_area {
+        let line: String = line
+            .iter()
+            .map(|cell| if *cell == EMPTY { '.' } else { '#' })
+            .collect();
+        println!("{line}");
+    }
+    println!(
+        "{locks} pieces locked, {} lines, {} points",
+        engine.lines, engine.points
+    );
+    // same seed and inputs always play out the same game
+    assert_eq!(engine.lines, 1);
+    assert_eq!(engine.points, 46);
+}
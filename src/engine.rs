@@ -0,0 +1,2317 @@
+use crate::{
+    game_mode::GameMode,
+    piece_bag::{PieceBag, Randomizer},
+    snapshot::Snapshot,
+    tetris_block::{PieceDef, PieceKind, TetrisBlock, SHAPES},
+};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, fmt, str::FromStr, time::Duration};
+
+/// Board cell without a block, blocks are `Some` palette index so any color can be one
+pub const EMPTY: Option<u8> = None;
+
+/// Color of garbage lines
+pub const GARBAGE: u8 = 7;
+
+/// Cleared lines raising the level by one
+pub const LINES_PER_LEVEL: u64 = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GameState {
+    /// Countdown before the first piece starts falling
+    Starting,
+    Playing,
+    Paused,
+    Finished,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveDirection {
+    Up,
+    Down,
+}
+
+/// Player input, for driving the game without a frontend through `GameEngine::apply`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Rotate,
+    MoveUp,
+    MoveDown,
+    SoftDrop,
+    HardDrop,
+    Hold,
+}
+
+/// What restarts the lock delay of a piece resting on the stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockReset {
+    /// Nothing, the piece locks once the delay passes after it lands
+    Classic,
+    /// Every successful move or rotation, the piece can be kept from locking forever
+    Step,
+    /// Successful moves and rotations, up to `max_lock_resets` per piece
+    Move,
+}
+
+impl FromStr for LockReset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "classic" => Ok(LockReset::Classic),
+            "step" => Ok(LockReset::Step),
+            "move" => Ok(LockReset::Move),
+            _ => Err(format!("unknown lock reset {s}")),
+        }
+    }
+}
+
+impl fmt::Display for LockReset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LockReset::Classic => "classic",
+            LockReset::Step => "step",
+            LockReset::Move => "move",
+        })
+    }
+}
+
+/// Options changing how the game plays
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rules {
+    pub randomizer: Randomizer,
+    /// Pieces spawn randomly rotated
+    pub random_rotation: bool,
+    /// Cells the first piece spawns away from the middle of the side axis, positive is up
+    pub spawn_offset: i32,
+    /// Color of each shape as a 256 color palette index, `None` picks a random one for every piece
+    pub colors: Option<[u8; SHAPES]>,
+    /// Custom piece set dealt instead of the tetrominoes, at most 256 pieces
+    pub pieces: Vec<PieceDef>,
+    pub start_level: u64,
+    /// Number of upcoming pieces known in advance, at least 1
+    pub preview_count: usize,
+    /// How long a piece can rest on the stack before it locks, zero locks it at once
+    pub lock_delay: Duration,
+    /// What restarts the lock delay
+    pub lock_reset: LockReset,
+    /// Moves and rotations per piece restarting the lock delay with `LockReset::Move`
+    pub max_lock_resets: u32,
+    /// How many locked pieces can be taken back
+    pub history_length: usize,
+    /// Times per game the top of the stack is cleared instead of losing
+    pub mercy_count: u32,
+    /// Lines cleared by each mercy
+    pub mercy_lines: usize,
+    /// Full lines are cleared only when no floating cells are above them
+    pub strict_clear: bool,
+    /// Clears score the same at every level instead of being multiplied by it
+    pub flat_scoring: bool,
+    /// Pieces moved off one side of the board appear on the other
+    pub wrap: bool,
+    /// Piece of this kind is held as it spawns while the hold is empty, a practice aid
+    pub auto_hold: Option<PieceKind>,
+    pub mode: GameMode,
+    /// Pieces locked between garbage lines in garbage mode
+    pub garbage_interval: u64,
+    /// Gaps in each garbage line
+    pub garbage_holes: usize,
+    /// How long full lines stay on the board before they're removed, zero removes them at once
+    pub clear_delay: Duration,
+    /// Pause after a lock (and its line clear) before the next piece comes in, zero spawns it at once
+    pub entry_delay: Duration,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            randomizer: Randomizer::Bag,
+            random_rotation: true,
+            spawn_offset: 0,
+            colors: None,
+            pieces: Vec::new(),
+            start_level: 1,
+            preview_count: 5,
+            lock_delay: Duration::from_millis(500),
+            lock_reset: LockReset::Move,
+            max_lock_resets: 15,
+            history_length: 5,
+            mercy_count: 0,
+            mercy_lines: 4,
+            strict_clear: false,
+            flat_scoring: false,
+            wrap: false,
+            auto_hold: None,
+            mode: GameMode::Endless,
+            garbage_interval: 8,
+            garbage_holes: 1,
+            clear_delay: Duration::ZERO,
+            entry_delay: Duration::ZERO,
+        }
+    }
+}
+
+/// Outcome of locking a piece
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockResult {
+    pub cleared: u32,
+    /// No room was left for the next piece, with an entry delay that's found out only when it spawns
+    pub lost: bool,
+    /// Piece took more inputs than it needed, see `GameEngine::finesse_minimum`
+    pub finesse_fault: bool,
+}
+
+/// Everything a locked piece changes, kept to take the lock back
+#[derive(Debug)]
+struct UndoStep {
+    snapshot: Snapshot,
+    placed: Vec<Vec<u64>>,
+    clears: [u64; 4],
+    spawned: Vec<u64>,
+    finesse_faults: u64,
+    combo: i32,
+    back_to_back: bool,
+    hold_used: bool,
+    mercies_left: u32,
+    rng: StdRng,
+    bag: PieceBag,
+}
+
+/// Board, pieces and score of a game, without any drawing or input handling.
+/// Gravity runs along x towards `game_width`, pieces spawn at x = 0
+#[derive(Debug)]
+pub struct GameEngine {
+    pub rules: Rules,
+    pub game_state: GameState,
+    pub game_width: usize,
+    pub game_height: usize,
+    pub filled_area: Vec<Vec<Option<u8>>>,
+    /// Round each cell of `filled_area` was filled in, moving with the cells as lines are removed
+    pub placed: Vec<Vec<u64>>,
+    pub current_block: TetrisBlock,
+    pub next_blocks: VecDeque<TetrisBlock>,
+    pub hold_block: Option<TetrisBlock>,
+    /// Hold was used for the current piece
+    pub hold_used: bool,
+    pub rounds: u64,
+    pub points: u64,
+    pub lines: u64,
+    pub level: u64,
+    pub combo: i32,
+    pub back_to_back: bool,
+    /// Number of single, double, triple and tetris clears, bigger clears of custom pieces
+    /// count as tetrises
+    pub clears: [u64; 4],
+    /// Pieces of each kind that became the current one, by `PieceKind::index`
+    pub spawned: Vec<u64>,
+    /// Rotations and side moves made with the current piece, counted by `count_input`
+    pub piece_inputs: u32,
+    /// Pieces placed with more inputs than `finesse_minimum` needs
+    pub finesse_faults: u64,
+    /// Game ended by reaching the goal of the mode rather than topping out
+    pub won: bool,
+    pub mercies_left: u32,
+    /// Time between gravity steps at the current level
+    pub move_interval: Duration,
+    /// Full lines waiting to be removed, as indices before any of them is removed
+    pub clearing: Vec<usize>,
+    pub seed: u64,
+    /// Something changed since the frontend last looked, cleared by the frontend
+    pub changed: bool,
+    lock_elapsed: Option<Duration>,
+    lock_resets: u32,
+    last_action_was_rotation: bool,
+    clearing_elapsed: Duration,
+    /// Time the next piece has been waiting for the entry delay, `None` once it's in play
+    entry_elapsed: Option<Duration>,
+    /// Current piece as it came in, finesse counts the inputs from there
+    entered_as: TetrisBlock,
+    rng: StdRng,
+    bag: PieceBag,
+    /// Games before the last locked pieces, the latest at the back
+    undo_steps: VecDeque<UndoStep>,
+    /// Steps taken back, the latest one last, until a lock starts a different future
+    redo_steps: Vec<UndoStep>,
+}
+
+impl GameEngine {
+    /// Game on a board `game_width` lines long, with pieces determined by the seed
+    pub fn new(rules: Rules, game_width: usize, game_height: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        // board and pieces are dealt by `restart`
+        let current_block = TetrisBlock::from_shape(0, &mut rng, false);
+        let entered_as = current_block.clone();
+        let mut engine = Self {
+            game_state: GameState::Playing,
+            game_width,
+            game_height,
+            filled_area: Vec::new(),
+            placed: Vec::new(),
+            current_block,
+            next_blocks: VecDeque::new(),
+            hold_block: None,
+            hold_used: false,
+            rounds: 0,
+            points: 0,
+            lines: 0,
+            level: rules.start_level,
+            combo: -1,
+            back_to_back: false,
+            clears: [0; 4],
+            spawned: Vec::new(),
+            piece_inputs: 0,
+            finesse_faults: 0,
+            won: false,
+            mercies_left: rules.mercy_count,
+            move_interval: gravity_interval(rules.start_level),
+            clearing: Vec::new(),
+            seed,
+            changed: true,
+            lock_elapsed: None,
+            lock_resets: 0,
+            last_action_was_rotation: false,
+            clearing_elapsed: Duration::ZERO,
+            entry_elapsed: None,
+            entered_as,
+            rng,
+            bag: PieceBag::new(rules.randomizer, shape_count(&rules)),
+            undo_steps: VecDeque::new(),
+            redo_steps: Vec::new(),
+            rules,
+        };
+        engine.restart(seed);
+        engine
+    }
+
+    /// Starts a new game in place with pieces from the seed
+    pub fn restart(&mut self, seed: u64) {
+        self.filled_area = vec![vec![EMPTY; self.game_height]; self.game_width];
+        self.placed = vec![vec![0; self.game_height]; self.game_width];
+
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+        self.bag = PieceBag::new(self.rules.randomizer, shape_count(&self.rules));
+
+        self.current_block = self.new_block();
+        self.next_blocks.clear();
+        for _ in 0..self.rules.preview_count.max(1) {
+            let block = self.new_block();
+            self.next_blocks.push_back(block);
+        }
+        self.current_block.pos = (0, self.centered_y(&self.current_block));
+        self.enter_current();
+
+        self.hold_block = None;
+        self.hold_used = false;
+        self.undo_steps.clear();
+        self.redo_steps.clear();
+        self.lock_elapsed = None;
+        self.lock_resets = 0;
+        self.last_action_was_rotation = false;
+        self.clearing.clear();
+        self.entry_elapsed = None;
+        self.rounds = 0;
+        self.points = 0;
+        self.lines = 0;
+        self.level = self.rules.start_level;
+        self.combo = -1;
+        self.back_to_back = false;
+        self.clears = [0; 4];
+        self.spawned = vec![0; SHAPES + self.rules.pieces.len()];
+        self.count_spawned();
+        self.finesse_faults = 0;
+        self.won = false;
+        self.move_interval = gravity_interval(self.level);
+        self.mercies_left = self.rules.mercy_count;
+        self.game_state = GameState::Playing;
+        self.changed = true;
+        // the first piece is dealt here, not by `spawn_next`
+        self.auto_hold();
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            width: self.game_width as u16,
+            height: self.game_height as u16,
+            seed: self.seed,
+            points: self.points,
+            rounds: self.rounds,
+            lines: self.lines,
+            level: self.level,
+            game_state: self.game_state.clone(),
+            move_interval: self.move_interval,
+            filled_area: self.filled_area.clone(),
+            current_block: self.current_block.clone(),
+            next_blocks: self.next_blocks.iter().cloned().collect(),
+            hold_block: self.hold_block.clone(),
+        }
+    }
+
+    /// Board, pieces and score from the snapshot, the game state is left to the caller
+    pub fn load_snapshot(&mut self, snapshot: Snapshot) {
+        self.points = snapshot.points;
+        self.rounds = snapshot.rounds;
+        self.lines = snapshot.lines;
+        self.level = snapshot.level;
+        self.move_interval = snapshot.move_interval;
+        self.placed = snapshot
+            .filled_area
+            .iter()
+            .map(|col| vec![snapshot.rounds; col.len()])
+            .collect();
+        self.filled_area = snapshot.filled_area;
+        self.current_block = snapshot.current_block;
+        self.next_blocks = snapshot.next_blocks.into();
+        self.hold_block = snapshot.hold_block;
+        // a piece held automatically by `restart` is replaced by the saved one
+        self.hold_used = false;
+        self.enter_current();
+        self.changed = true;
+    }
+
+    /// Takes back the last locked piece, it returns to the spawn edge on the same side position.
+    /// The game as it was can be brought back with `redo` until the next lock.
+    /// Returns whether there was anything to undo
+    pub fn undo(&mut self) -> bool {
+        if self.game_state == GameState::Finished {
+            return false;
+        }
+        let Some(step) = self.undo_steps.pop_back() else {
+            return false;
+        };
+        self.redo_steps.push(self.history_step());
+        self.restore_step(step);
+        true
+    }
+
+    /// Brings back the game as it was before the last `undo`.
+    /// Returns whether there was anything to redo
+    pub fn redo(&mut self) -> bool {
+        if self.game_state == GameState::Finished {
+            return false;
+        }
+        let Some(step) = self.redo_steps.pop() else {
+            return false;
+        };
+        let current = self.history_step();
+        self.undo_steps.push_back(current);
+        self.restore_step(step);
+        true
+    }
+
+    /// Locks that can be taken back and brought back again
+    pub fn history(&self) -> (usize, usize) {
+        (self.undo_steps.len(), self.redo_steps.len())
+    }
+
+    /// Drops the steps `redo` could bring back, playing on from an earlier point
+    pub fn clear_redo(&mut self) {
+        self.redo_steps.clear();
+    }
+
+    fn history_step(&self) -> UndoStep {
+        UndoStep {
+            snapshot: self.snapshot(),
+            placed: self.placed.clone(),
+            clears: self.clears,
+            spawned: self.spawned.clone(),
+            finesse_faults: self.finesse_faults,
+            combo: self.combo,
+            back_to_back: self.back_to_back,
+            hold_used: self.hold_used,
+            mercies_left: self.mercies_left,
+            rng: self.rng.clone(),
+            bag: self.bag.clone(),
+        }
+    }
+
+    /// Game as it was at the step, with the piece back at the spawn edge
+    fn restore_step(&mut self, step: UndoStep) {
+        self.load_snapshot(step.snapshot);
+        self.placed = step.placed;
+        self.clears = step.clears;
+        self.spawned = step.spawned;
+        self.finesse_faults = step.finesse_faults;
+        self.combo = step.combo;
+        self.back_to_back = step.back_to_back;
+        self.hold_used = step.hold_used;
+        self.mercies_left = step.mercies_left;
+        self.rng = step.rng;
+        self.bag = step.bag;
+
+        let spawn_pos = (0, self.current_block.pos.1);
+        if self.fits(&self.current_block.pattern, spawn_pos) {
+            self.current_block.pos = spawn_pos;
+        }
+        self.enter_current();
+        self.lock_elapsed = None;
+        self.lock_resets = 0;
+        self.last_action_was_rotation = false;
+        self.entry_elapsed = None;
+    }
+
+    /// Empties the board keeping the score, the falling piece goes back to the spawn edge.
+    /// Returns whether there was a piece falling
+    pub fn clear_board(&mut self) -> bool {
+        if matches!(self.game_state, GameState::Starting | GameState::Finished)
+            || self.is_between_pieces()
+        {
+            return false;
+        }
+        for (col, placed) in self.filled_area.iter_mut().zip(&mut self.placed) {
+            col.iter_mut().for_each(|c| *c = EMPTY);
+            placed.iter_mut().for_each(|round| *round = 0);
+        }
+        self.current_block.pos = (0, self.current_block.pos.1);
+        self.enter_current();
+        self.lock_elapsed = None;
+        self.lock_resets = 0;
+        self.last_action_was_rotation = false;
+        self.redo_steps.clear();
+        self.changed = true;
+        true
+    }
+
+    /// Grows the board, the stack stays on the floor and the pieces keep their distance
+    /// from the spawn edge. Locks can't be undone past a resize. Returns whether it grew
+    pub fn grow(&mut self, width: usize, height: usize) -> bool {
+        if width < self.game_width
+            || height < self.game_height
+            || (width, height) == (self.game_width, self.game_height)
+        {
+            return false;
+        }
+        let shift = width - self.game_width;
+        let mut filled_area = vec![vec![EMPTY; height]; width];
+        let mut placed = vec![vec![0; height]; width];
+        for (x, col) in self.filled_area.iter().enumerate() {
+            filled_area[x + shift][..col.len()].copy_from_slice(col);
+            placed[x + shift][..col.len()].copy_from_slice(&self.placed[x]);
+        }
+        self.filled_area = filled_area;
+        self.placed = placed;
+        self.clearing.iter_mut().for_each(|x| *x += shift);
+        self.game_width = width;
+        self.game_height = height;
+        self.undo_steps.clear();
+        self.redo_steps.clear();
+        self.changed = true;
+        true
+    }
+
+    pub fn end_game(&mut self, won: bool) {
+        self.game_state = GameState::Finished;
+        self.won = won;
+        self.changed = true;
+    }
+
+    /// Places the current piece on the board, scores cleared lines and brings in the next piece
+    pub fn finish_round(&mut self) -> LockResult {
+        self.changed = true;
+        let step = self.history_step();
+        self.undo_steps.push_back(step);
+        if self.undo_steps.len() > self.rules.history_length {
+            self.undo_steps.pop_front();
+        }
+        // a new lock starts a different future
+        self.redo_steps.clear();
+
+        let (x, y) = self.current_block.pos;
+        let x = x as usize;
+        let y = y as usize;
+
+        let t_spin = self.is_t_spin();
+        let finesse_fault = self.piece_inputs > self.finesse_minimum();
+        if finesse_fault {
+            self.finesse_faults = self.finesse_faults.saturating_add(1);
+        }
+        let block = self.current_block.clone();
+        for (i, col) in block.pattern.iter().enumerate() {
+            for (j, draw) in col.iter().enumerate() {
+                if *draw {
+                    let y = self.cell_y((y + j) as i32).unwrap();
+                    self.filled_area[x + i][y] = Some(block.color);
+                    self.placed[x + i][y] = self.rounds;
+                }
+            }
+        }
+
+        let lines = self.full_lines();
+        let cleared_cols = lines.len() as u64;
+        let clear_type = lines.len().min(self.clears.len());
+        if let Some(count) = self.clears.get_mut(clear_type.wrapping_sub(1)) {
+            *count += 1;
+        }
+
+        let mut points: u64 = match (t_spin, cleared_cols) {
+            (true, 1) => 800,
+            (true, 2) => 1200,
+            (true, 3) => 1600,
+            (_, 1) => 40,
+            (_, 2) => 100,
+            (_, 3) => 300,
+            (_, 4) => 1200,
+            // only custom pieces clear more, each line past a tetris adds a quarter of one
+            (_, lines @ 5..) => 300 * lines,
+            _ => 0,
+        };
+        // levels start at 1 where the classic NES table, multiplied by level + 1, starts at 0
+        if !self.rules.flat_scoring {
+            points = points.saturating_mul(self.level.max(1));
+        }
+
+        if cleared_cols > 0 {
+            // tetrises and t-spins in a row get half more, any other clear breaks the chain
+            let difficult = t_spin || cleared_cols >= 4;
+            if difficult && self.back_to_back {
+                points = points.saturating_mul(3) / 2;
+            }
+            self.back_to_back = difficult;
+
+            // every clearing lock in a row adds to the combo
+            self.combo = self.combo.saturating_add(1);
+            let mut combo_bonus = 50 * self.combo as u64;
+            if !self.rules.flat_scoring {
+                combo_bonus = combo_bonus.saturating_mul(self.level);
+            }
+            points = points.saturating_add(combo_bonus);
+        } else {
+            self.combo = -1;
+        }
+        // endless games stop counting up instead of wrapping around
+        self.points = self.points.saturating_add(points);
+
+        // gravity picks up the new interval with the next step
+        self.lines = self.lines.saturating_add(cleared_cols);
+        // zen games stay at the level they started at
+        if self.rules.mode != GameMode::Zen {
+            self.level = self
+                .rules
+                .start_level
+                .saturating_add(self.lines / LINES_PER_LEVEL);
+            self.move_interval = gravity_interval(self.level);
+        }
+
+        self.rounds = self.rounds.saturating_add(1);
+        self.lock_elapsed = None;
+        self.lock_resets = 0;
+        self.last_action_was_rotation = false;
+
+        if self.rules.mode.is_complete(self.lines, self.level) {
+            self.collapse(&lines);
+            self.end_game(true);
+        } else if lines.is_empty() {
+            self.enter_next();
+        } else if self.rules.clear_delay.is_zero() {
+            self.collapse(&lines);
+            self.enter_next();
+        } else {
+            // lines stay on the board for a moment, `tick_clear` removes them
+            self.clearing = lines;
+            self.clearing_elapsed = Duration::ZERO;
+        }
+
+        LockResult {
+            cleared: cleared_cols as u32,
+            lost: self.game_state == GameState::Finished && !self.won,
+            finesse_fault,
+        }
+    }
+
+    /// Lines completed by the locked piece, as indices on the board before any of them is removed
+    fn full_lines(&mut self) -> Vec<usize> {
+        if !self.rules.strict_clear {
+            let x = self.current_block.pos.0 as usize;
+            return (x..x + self.current_block.pattern.len())
+                .filter(|line| self.is_line_full(*line))
+                .collect();
+        }
+
+        // full lines with floating cells above wait until they're supported,
+        // clearing one can support another, so clears are played out and undone
+        let board = self.filled_area.clone();
+        let placed = self.placed.clone();
+        let mut origin: Vec<Option<usize>> = (0..self.game_width).map(Some).collect();
+        let mut lines = Vec::new();
+        while let Some(line) = (0..self.game_width)
+            .rev()
+            .find(|line| self.is_line_full(*line) && self.is_line_supported(*line))
+        {
+            lines.extend(origin[line].take());
+            origin[..line + 1].rotate_right(1);
+            self.clear_line(line);
+        }
+        self.filled_area = board;
+        self.placed = placed;
+        lines
+    }
+
+    /// Removes the lines, given as indices before any of them is removed
+    fn collapse(&mut self, lines: &[usize]) {
+        let mut lines = lines.to_vec();
+        // removing a line moves only the ones before it
+        lines.sort_unstable();
+        for line in lines {
+            self.clear_line(line);
+        }
+    }
+
+    pub fn is_clearing(&self) -> bool {
+        !self.clearing.is_empty()
+    }
+
+    /// Counts time cleared lines are shown, then removes them and spawns the next piece.
+    /// Returns whether the lines were removed
+    pub fn tick_clear(&mut self, elapsed: Duration) -> bool {
+        if !self.is_clearing() {
+            return false;
+        }
+        self.clearing_elapsed += elapsed;
+        if self.clearing_elapsed < self.rules.clear_delay {
+            return false;
+        }
+        let lines = std::mem::take(&mut self.clearing);
+        self.collapse(&lines);
+        self.enter_next();
+        self.changed = true;
+        true
+    }
+
+    /// Brings in the next piece, after the entry delay if there's one
+    fn enter_next(&mut self) {
+        if self.rules.entry_delay.is_zero() {
+            self.spawn_next();
+        } else {
+            self.entry_elapsed = Some(Duration::ZERO);
+        }
+    }
+
+    /// Locked piece waits for the entry delay before the next one comes in
+    pub fn is_entering(&self) -> bool {
+        self.entry_elapsed.is_some()
+    }
+
+    /// No piece is falling, cleared lines are shown or the next piece waits for the entry delay
+    pub fn is_between_pieces(&self) -> bool {
+        self.is_clearing() || self.is_entering()
+    }
+
+    /// Counts the entry delay, then spawns the next piece. Returns whether it spawned
+    pub fn tick_entry(&mut self, elapsed: Duration) -> bool {
+        let Some(waited) = self.entry_elapsed.as_mut() else {
+            return false;
+        };
+        *waited += elapsed;
+        if *waited < self.rules.entry_delay {
+            return false;
+        }
+        self.skip_entry()
+    }
+
+    /// Spawns the piece waiting for the entry delay at once. Returns whether one was waiting
+    pub fn skip_entry(&mut self) -> bool {
+        if self.entry_elapsed.take().is_none() {
+            return false;
+        }
+        self.spawn_next();
+        self.changed = true;
+        true
+    }
+
+    /// Next piece takes over, spending a mercy or ending the game if there's no room for it
+    fn spawn_next(&mut self) {
+        let interval = self.rules.garbage_interval.max(1);
+        if self.rules.mode == GameMode::Garbage
+            && self.rounds.is_multiple_of(interval)
+            && !self.add_garbage()
+        {
+            return self.end_game(false);
+        }
+
+        let starting_y_pos = self.next_spawn_y();
+
+        // zen games never end, the stack in the way is cleared instead
+        while self.rules.mode == GameMode::Zen
+            && self.spawn_blocked(starting_y_pos)
+            && self.clear_top_lines(1)
+        {}
+        while self.spawn_blocked(starting_y_pos) {
+            if self.mercies_left == 0 {
+                return self.end_game(false);
+            }
+            self.mercies_left -= 1;
+            self.clear_top_lines(self.rules.mercy_lines.max(1));
+        }
+
+        self.current_block = self.pop_next();
+        self.current_block.pos = (0, starting_y_pos as i32);
+        self.enter_current();
+        self.count_spawned();
+        self.hold_used = false;
+        self.auto_hold();
+    }
+
+    /// Holds the piece that just spawned if it's the kind `Rules::auto_hold` asks for,
+    /// only into an empty hold that wasn't used for this piece
+    fn auto_hold(&mut self) {
+        if self.rules.auto_hold == Some(self.current_block.kind)
+            && self.hold_block.is_none()
+            && !self.hold_used
+        {
+            self.hold();
+        }
+    }
+
+    /// Pieces of a custom set missing from this game, like ones of a loaded save, aren't counted
+    fn count_spawned(&mut self) {
+        if let Some(count) = self.spawned.get_mut(self.current_block.kind.index()) {
+            *count += 1;
+        }
+    }
+
+    /// Current piece just came in, finesse starts counting its inputs
+    fn enter_current(&mut self) {
+        self.entered_as = self.current_block.clone();
+        self.piece_inputs = 0;
+    }
+
+    /// Counts a rotation or side move the player made towards finesse,
+    /// a held key repeating a move counts once
+    pub fn count_input(&mut self) {
+        self.piece_inputs = self.piece_inputs.saturating_add(1);
+    }
+
+    /// Fewest rotations and side moves bringing the current piece from how it came in
+    /// to where it is now on an empty board, holding a move key until the wall counts as one
+    pub fn finesse_minimum(&self) -> u32 {
+        let (target, target_first, _) = normalized(&self.current_block.pattern);
+        let target_y = self.current_block.pos.1 + target_first as i32;
+
+        let mut pattern = self.entered_as.pattern.clone();
+        let mut y = self.entered_as.pos.1;
+        let mut fewest = u32::MAX;
+        for turns in 0..4 {
+            let (cells, first, last) = normalized(&pattern);
+            if cells == target {
+                let distance = (y + first as i32).abs_diff(target_y);
+                let moves = if self.rules.wrap {
+                    distance.min(self.game_height as u32 - distance)
+                } else {
+                    // holding to either wall, then tapping back from it
+                    let far_wall = self.game_height as i32 - 1 - (last - first) as i32;
+                    let from_wall = target_y.abs_diff(0).min(target_y.abs_diff(far_wall));
+                    distance.min(1 + from_wall)
+                };
+                fewest = fewest.min(turns + moves);
+            }
+            // turned like `rotate90` does without kicks, keeping the middle in place
+            let turned = TetrisBlock::rotate90(&pattern);
+            let (width, height) = (turned[0].len() as i32, turned.len() as i32);
+            y += (height - width) / 2;
+            pattern = turned;
+        }
+        fewest
+    }
+
+    /// T piece locked right after a rotation, with at least three of the four cells
+    /// diagonal to its center occupied (walls and floor count as occupied)
+    fn is_t_spin(&self) -> bool {
+        let block = &self.current_block;
+        if !block.is_t() || !self.last_action_was_rotation {
+            return false;
+        }
+        let Some((i, j)) = block.center() else {
+            return false;
+        };
+        let (x, y) = (block.pos.0 + i as i32, block.pos.1 + j as i32);
+        let occupied = |x: i32, y: i32| {
+            x < 0
+                || x as usize >= self.game_width
+                || self
+                    .cell_y(y)
+                    .is_none_or(|y| self.filled_area[x as usize][y] != EMPTY)
+        };
+        [(-1, -1), (-1, 1), (1, -1), (1, 1)]
+            .iter()
+            .filter(|(dx, dy)| occupied(x + dx, y + dy))
+            .count()
+            >= 3
+    }
+
+    /// Kinds of pieces the game deals, the custom set if there is one
+    pub fn kinds(&self) -> Vec<PieceKind> {
+        match self.rules.pieces.is_empty() {
+            true => PieceKind::ALL.to_vec(),
+            false => (0..shape_count(&self.rules))
+                .map(|index| PieceKind::Custom(index as u8))
+                .collect(),
+        }
+    }
+
+    fn new_block(&mut self) -> TetrisBlock {
+        let shape = self.bag.next_shape(&mut self.rng);
+        if let Some(def) = self.rules.pieces.get(shape) {
+            let random_rotation = self.rules.random_rotation;
+            return TetrisBlock::from_def(def, shape as u8, &mut self.rng, random_rotation);
+        }
+        let mut block = TetrisBlock::from_shape(shape, &mut self.rng, self.rules.random_rotation);
+        if let Some(colors) = &self.rules.colors {
+            block.color = colors[block.kind.index()];
+        }
+        block
+    }
+
+    /// Takes the first upcoming piece, refilling the queue at the back
+    fn pop_next(&mut self) -> TetrisBlock {
+        let new_block = self.new_block();
+        self.next_blocks.push_back(new_block);
+        self.next_blocks.pop_front().unwrap()
+    }
+
+    pub fn next_block(&self) -> &TetrisBlock {
+        &self.next_blocks[0]
+    }
+
+    /// Next piece spawns where the current one is on the side axis, kept within the board
+    pub fn next_spawn_y(&self) -> usize {
+        self.spawn_y(self.next_block())
+    }
+
+    /// Side axis position putting the middle of the block's filled cells in the middle
+    /// of the board, moved by `spawn_offset` as far as the board allows
+    fn centered_y(&self, block: &TetrisBlock) -> i32 {
+        let filled = || block.pattern.iter().flat_map(|col| col.iter().enumerate());
+        let first = filled()
+            .filter(|(_, filled)| **filled)
+            .map(|(j, _)| j)
+            .min();
+        let last = filled()
+            .filter(|(_, filled)| **filled)
+            .map(|(j, _)| j)
+            .max();
+        let (Some(first), Some(last)) = (first, last) else {
+            return 0;
+        };
+        let (first, last) = (first as i32, last as i32);
+        let y =
+            (self.game_height as i32 - (last - first + 1)) / 2 - first + self.rules.spawn_offset;
+        y.clamp(-first, (self.game_height as i32 - 1 - last).max(-first))
+    }
+
+    /// Side axis position keeping every filled cell of the block on the board,
+    /// trailing empty cells of a column may stick out
+    fn spawn_y(&self, block: &TetrisBlock) -> usize {
+        let extent = block
+            .pattern
+            .iter()
+            .filter_map(|col| col.iter().rposition(|filled| *filled))
+            .map(|last| last + 1)
+            .max()
+            .unwrap_or(0);
+        (self.current_block.pos.1.max(0) as usize).min(self.game_height.saturating_sub(extent))
+    }
+
+    /// Swaps current piece with the held one (or the next one if nothing is held yet),
+    /// can be used once per piece. Returns whether the pieces were swapped
+    pub fn hold(&mut self) -> bool {
+        if self.game_state == GameState::Finished || self.hold_used {
+            return false;
+        }
+        if self.game_state == GameState::Paused {
+            self.game_state = GameState::Playing;
+        }
+
+        let incoming = match self.hold_block.take() {
+            Some(block) => block,
+            None => self.pop_next(),
+        };
+        let starting_y_pos = self.spawn_y(&incoming);
+        let held = std::mem::replace(&mut self.current_block, incoming);
+        self.current_block.pos = (0, starting_y_pos as i32);
+        self.hold_block = Some(held);
+        self.hold_used = self.rules.mode != GameMode::Zen;
+        self.enter_current();
+        self.lock_elapsed = None;
+        self.lock_resets = 0;
+        self.last_action_was_rotation = false;
+        self.changed = true;
+
+        while self.rules.mode == GameMode::Zen
+            && !self.fits(&self.current_block.pattern, self.current_block.pos)
+            && self.clear_top_lines(1)
+        {}
+        if !self.fits(&self.current_block.pattern, self.current_block.pos) {
+            self.end_game(false);
+        }
+        true
+    }
+
+    fn is_line_full(&self, x: usize) -> bool {
+        self.filled_area[x].iter().all(|c| *c != EMPTY)
+    }
+
+    /// No floating cells above the line, each filled cell between it and the spawn edge rests on another
+    fn is_line_supported(&self, x: usize) -> bool {
+        (0..self.game_height).all(|y| {
+            let mut filled = false;
+            self.filled_area[..x].iter().all(|col| {
+                let empty = col[y] == EMPTY;
+                let hole = filled && empty;
+                filled |= !empty;
+                !hole
+            })
+        })
+    }
+
+    /// Removes the line, moving everything between it and the spawn edge one line towards the floor
+    fn clear_line(&mut self, x: usize) {
+        self.filled_area[x].iter_mut().for_each(|c| *c = EMPTY);
+        self.filled_area[..x + 1].rotate_right(1);
+        self.placed[..x + 1].rotate_right(1);
+    }
+
+    /// Pushes the stack one line towards the spawn edge and fills the freed far line
+    /// except for random gaps, fails when the stack would be pushed off the board
+    fn add_garbage(&mut self) -> bool {
+        if self.filled_area[0].iter().any(|c| *c != EMPTY) {
+            return false;
+        }
+        let holes = self.rules.garbage_holes.clamp(1, self.game_height - 1);
+        let gaps = rand::seq::index::sample(&mut self.rng, self.game_height, holes);
+        let last = self.game_width - 1;
+        self.filled_area.rotate_left(1);
+        self.filled_area[last] = vec![Some(GARBAGE); self.game_height];
+        // garbage counts as filled with the lock that raised it
+        self.placed.rotate_left(1);
+        self.placed[last] = vec![self.rounds.saturating_sub(1); self.game_height];
+        for y in gaps {
+            self.filled_area[last][y] = EMPTY;
+        }
+        self.changed = true;
+        true
+    }
+
+    fn spawn_blocked(&self, starting_y_pos: usize) -> bool {
+        !self.fits(&self.next_block().pattern, (0, starting_y_pos as i32))
+    }
+
+    /// Maps side axis position to board column, wrapping around the edges in wrap mode
+    pub fn cell_y(&self, y: i32) -> Option<usize> {
+        if self.rules.wrap {
+            Some(y.rem_euclid(self.game_height as i32) as usize)
+        } else if 0 <= y && (y as usize) < self.game_height {
+            Some(y as usize)
+        } else {
+            None
+        }
+    }
+
+    /// In wrap mode keeps side axis position of a piece within the board
+    fn wrap_pos(&self, y: i32) -> i32 {
+        if self.rules.wrap {
+            y.rem_euclid(self.game_height as i32)
+        } else {
+            y
+        }
+    }
+
+    /// Pattern placed at `pos` is inside the board and doesn't overlap the stack
+    pub fn fits(&self, pattern: &[Vec<bool>], pos: (i32, i32)) -> bool {
+        pattern.iter().enumerate().all(|(i, col)| {
+            col.iter().enumerate().all(|(j, draw)| {
+                let x = pos.0 + i as i32;
+                !*draw
+                    || (0 <= x
+                        && (x as usize) < self.game_width
+                        && self
+                            .cell_y(pos.1 + j as i32)
+                            .is_some_and(|y| self.filled_area[x as usize][y] == EMPTY))
+            })
+        })
+    }
+
+    /// Empties `count` lines of the stack closest to the spawn edge,
+    /// returns whether there was a stack to clear
+    fn clear_top_lines(&mut self, count: usize) -> bool {
+        let Some(top) = self
+            .filled_area
+            .iter()
+            .position(|col| col.iter().any(|c| *c != EMPTY))
+        else {
+            return false;
+        };
+        for col in self.filled_area.iter_mut().skip(top).take(count) {
+            col.iter_mut().for_each(|c| *c = EMPTY);
+        }
+        true
+    }
+
+    /// Turns the current piece, kicking it off walls and the stack if needed.
+    /// Returns whether it turned
+    pub fn rotate90(&mut self) -> bool {
+        if self.game_state == GameState::Finished {
+            return false;
+        }
+        if self.game_state == GameState::Paused {
+            self.game_state = GameState::Playing;
+            self.changed = true;
+        }
+
+        let new_pattern = TetrisBlock::rotate90(&self.current_block.pattern);
+
+        // keeps the middle of the piece in place, so long pieces don't drift to one side
+        let (width, height) = (new_pattern[0].len() as i32, new_pattern.len() as i32);
+        let (x, y) = self.current_block.pos;
+        let (x, y) = (x + (width - height) / 2, y + (height - width) / 2);
+
+        let mut positions: Vec<_> = self
+            .current_block
+            .kicks()
+            .map(|(move_x, move_y)| (x + move_x, y + move_y))
+            .collect();
+        // long pieces turned flush against a side wall stick out further than the kicks reach,
+        // pushing them back onto the board is the last resort
+        if !self.rules.wrap {
+            let max_y = self.game_height as i32 - width;
+            positions.push((x, y.clamp(0, max_y.max(0))));
+        }
+        for pos in positions {
+            if self.fits(&new_pattern, pos) {
+                self.current_block.pattern = new_pattern;
+                self.current_block.pos = (pos.0, self.wrap_pos(pos.1));
+                self.current_block.orientation = (self.current_block.orientation + 1) % 4;
+                self.last_action_was_rotation = true;
+                self.reset_lock_delay();
+                self.changed = true;
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn is_grounded(&self) -> bool {
+        let (x, y) = self.current_block.pos;
+        !self.fits(&self.current_block.pattern, (x + 1, y))
+    }
+
+    /// Successful move or rotation of a resting piece restarts the lock delay as the rules allow,
+    /// by default a limited number of times per piece so it can't be stalled forever
+    fn reset_lock_delay(&mut self) {
+        let allowed = match self.rules.lock_reset {
+            LockReset::Classic => false,
+            LockReset::Step => true,
+            LockReset::Move => self.lock_resets < self.rules.max_lock_resets,
+        };
+        if self.lock_elapsed.is_none() || !allowed {
+            return;
+        }
+        self.lock_resets = self.lock_resets.saturating_add(1);
+        self.lock_elapsed = self.is_grounded().then_some(Duration::ZERO);
+    }
+
+    /// Counts time the piece rests on the stack, locks it once the lock delay passes
+    pub fn tick_lock(&mut self, elapsed: Duration) -> Option<LockResult> {
+        let resting = self.lock_elapsed.as_mut()?;
+        *resting += elapsed;
+        if *resting < self.rules.lock_delay {
+            return None;
+        }
+        if !self.is_grounded() {
+            // slid off the ledge, gravity takes over again
+            self.lock_elapsed = None;
+            return None;
+        }
+        Some(self.finish_round())
+    }
+
+    /// Moves the piece one line towards the floor, a resting piece starts its lock delay
+    /// or locks at once without one
+    pub fn move_forward(&mut self) -> Option<LockResult> {
+        if self.game_state == GameState::Finished {
+            return None;
+        }
+        if self.game_state == GameState::Paused {
+            self.game_state = GameState::Playing;
+            self.changed = true;
+        }
+
+        if self.is_grounded() {
+            // resting pieces lock after the delay, counted by `tick_lock`
+            if self.rules.lock_delay.is_zero() {
+                return Some(self.finish_round());
+            } else if self.lock_elapsed.is_none() {
+                self.lock_elapsed = Some(Duration::ZERO);
+            }
+            return None;
+        }
+        self.current_block.pos.0 += 1;
+        self.lock_elapsed = None;
+        self.last_action_was_rotation = false;
+        self.changed = true;
+        None
+    }
+
+    /// Moves the piece forward like gravity, scoring a point for the cell it fell
+    pub fn soft_drop(&mut self) -> Option<LockResult> {
+        let falls = self.game_state != GameState::Finished && !self.is_grounded();
+        let result = self.move_forward();
+        if falls {
+            self.points = self.points.saturating_add(1);
+        }
+        result
+    }
+
+    /// Drops the piece to where it lands and locks it
+    pub fn move_till_end(&mut self) -> Option<LockResult> {
+        if self.game_state == GameState::Finished {
+            return None;
+        }
+        if self.game_state == GameState::Paused {
+            self.game_state = GameState::Playing;
+            self.changed = true;
+        }
+
+        let end_x = self.get_end_move_pos().0;
+        if end_x != self.current_block.pos.0 {
+            self.current_block.pos.0 = end_x;
+            self.last_action_was_rotation = false;
+        }
+        Some(self.finish_round())
+    }
+
+    /// Moves the piece along the side axis. Returns whether it moved
+    pub fn move_side(&mut self, direction: MoveDirection) -> bool {
+        if self.game_state == GameState::Finished {
+            return false;
+        }
+        if self.game_state == GameState::Paused {
+            self.game_state = GameState::Playing;
+            self.changed = true;
+        }
+
+        let (x, y) = self.current_block.pos;
+        let direction = match direction {
+            MoveDirection::Down => -1,
+            MoveDirection::Up => 1,
+        };
+        if !self.fits(&self.current_block.pattern, (x, y + direction)) {
+            // can't move there
+            return false;
+        }
+        // in wrap mode position is kept within the board, so the piece crosses the edge
+        self.current_block.pos.1 = self.wrap_pos(y + direction);
+        self.last_action_was_rotation = false;
+        self.reset_lock_delay();
+        self.changed = true;
+        true
+    }
+
+    /// Position where the current piece lands if dropped
+    pub fn get_end_move_pos(&self) -> (i32, i32) {
+        let (mut x, y) = self.current_block.pos;
+        while self.fits(&self.current_block.pattern, (x + 1, y)) {
+            x += 1;
+        }
+        (x, y)
+    }
+
+    /// Applies player input, ignored between pieces. Returns the lock it caused
+    pub fn apply(&mut self, action: Action) -> Option<LockResult> {
+        if self.is_between_pieces() {
+            return None;
+        }
+        match action {
+            Action::Rotate => {
+                self.count_input();
+                self.rotate90();
+                None
+            }
+            Action::MoveUp => {
+                self.count_input();
+                self.move_side(MoveDirection::Up);
+                None
+            }
+            Action::MoveDown => {
+                self.count_input();
+                self.move_side(MoveDirection::Down);
+                None
+            }
+            Action::SoftDrop => self.soft_drop(),
+            Action::HardDrop => self.move_till_end(),
+            Action::Hold => {
+                self.hold();
+                None
+            }
+        }
+    }
+
+    /// Lets one gravity interval pass without input, as a frontend would: removes shown lines,
+    /// counts entry and lock delays and moves the piece one line. Returns the lock it caused
+    pub fn tick(&mut self) -> Option<LockResult> {
+        let elapsed = self.move_interval;
+        if self.tick_clear(elapsed) || self.is_clearing() {
+            return None;
+        }
+        if self.tick_entry(elapsed) || self.is_entering() {
+            return None;
+        }
+        if let Some(result) = self.tick_lock(elapsed) {
+            return Some(result);
+        }
+        self.move_forward()
+    }
+
+    /// Height of the settled stack for each side axis column,
+    /// measured from the floor to the filled cell closest to the spawn edge
+    pub fn height_profile(&self) -> Vec<usize> {
+        height_profile(&self.filled_area)
+    }
+
+    /// Number of empty cells that have a filled cell between them and the spawn edge
+    pub fn holes(&self) -> usize {
+        holes(&self.filled_area)
+    }
+}
+
+/// Number of shapes the bag picks from
+fn shape_count(rules: &Rules) -> usize {
+    match rules.pieces.len() {
+        0 => SHAPES,
+        count => count.min(u8::MAX as usize + 1),
+    }
+}
+
+/// Filled cells of the pattern moved so the first row and column with one are at 0,
+/// with the first and last side axis index holding a cell
+fn normalized(pattern: &[Vec<bool>]) -> (Vec<(usize, usize)>, usize, usize) {
+    let cells: Vec<(usize, usize)> = pattern
+        .iter()
+        .enumerate()
+        .flat_map(|(i, col)| {
+            col.iter()
+                .enumerate()
+                .filter(|(_, filled)| **filled)
+                .map(move |(j, _)| (i, j))
+        })
+        .collect();
+    let first_i = cells.iter().map(|(i, _)| *i).min().unwrap_or(0);
+    let first_j = cells.iter().map(|(_, j)| *j).min().unwrap_or(0);
+    let last_j = cells.iter().map(|(_, j)| *j).max().unwrap_or(0);
+    let mut cells: Vec<_> = cells
+        .into_iter()
+        .map(|(i, j)| (i - first_i, j - first_j))
+        .collect();
+    cells.sort_unstable();
+    (cells, first_j, last_j)
+}
+
+/// Time between gravity steps, frames per row of the classic NES curve scaled so that
+/// level 1 moves a piece every 0.1s
+pub fn gravity_interval(level: u64) -> Duration {
+    let frames = match level.saturating_sub(1) {
+        level @ 0..=8 => 48 - 5 * level,
+        9 => 6,
+        10..=12 => 5,
+        13..=15 => 4,
+        16..=18 => 3,
+        19..=28 => 2,
+        _ => 1,
+    };
+    Duration::from_secs_f64(0.1 * frames as f64 / 48.0)
+}
+
+pub fn height_profile(board: &[Vec<Option<u8>>]) -> Vec<usize> {
+    let mut profile = vec![0; board.first().map_or(0, |col| col.len())];
+    for (x, col) in board.iter().enumerate() {
+        for (y, color) in col.iter().enumerate() {
+            if *color != EMPTY && profile[y] == 0 {
+                profile[y] = board.len() - x;
+            }
+        }
+    }
+    profile
+}
+
+pub fn holes(board: &[Vec<Option<u8>>]) -> usize {
+    let mut covered = vec![false; board.first().map_or(0, |col| col.len())];
+    let mut holes = 0;
+    for col in board.iter() {
+        for (y, color) in col.iter().enumerate() {
+            if *color != EMPTY {
+                covered[y] = true;
+            } else if covered[y] {
+                holes += 1;
+            }
+        }
+    }
+    holes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const I: usize = 0;
+    const O: usize = 1;
+
+    fn engine(width: usize, height: usize) -> GameEngine {
+        let rules = Rules {
+            random_rotation: false,
+            ..Rules::default()
+        };
+        GameEngine::new(rules, width, height, 1)
+    }
+
+    /// Board lines from the spawn edge to the floor, `X` for a filled cell
+    fn set_board(engine: &mut GameEngine, lines: &[&str]) {
+        engine.filled_area = lines
+            .iter()
+            .map(|line| {
+                line.chars()
+                    .map(|c| if c == 'X' { Some(9) } else { EMPTY })
+                    .collect()
+            })
+            .collect();
+    }
+
+    fn board(engine: &GameEngine) -> Vec<String> {
+        engine
+            .filled_area
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .map(|c| if *c == EMPTY { '.' } else { 'X' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Puts a piece of the shape, turned `turns` times, at the position as the current one
+    fn place(engine: &mut GameEngine, shape: usize, turns: usize, pos: (i32, i32)) {
+        let mut block = TetrisBlock::from_shape(shape, &mut StdRng::seed_from_u64(0), false);
+        for _ in 0..turns {
+            block.pattern = TetrisBlock::rotate90(&block.pattern);
+        }
+        block.orientation = turns;
+        block.pos = pos;
+        engine.current_block = block;
+    }
+
+    #[test]
+    fn clears_one_to_four_lines() {
+        for (cleared, points) in [(1, 40), (2, 100), (3, 300), (4, 1200)] {
+            let mut engine = engine(8, 4);
+            let mut lines = vec!["...."; 8];
+            for line in &mut lines[8 - cleared..] {
+                *line = ".XXX";
+            }
+            set_board(&mut engine, &lines);
+            // upright I piece fills the first cell of the last four lines
+            place(&mut engine, I, 1, (4, 0));
+
+            let result = engine.finish_round();
+
+            assert_eq!(
+                result,
+                LockResult {
+                    cleared: cleared as u32,
+                    lost: false,
+                    finesse_fault: false,
+                }
+            );
+            assert_eq!(engine.points, points);
+            assert_eq!(engine.lines, cleared as u64);
+            // cells of the piece in uncleared lines moved to the floor
+            let mut expected = vec!["...."; 8];
+            for line in &mut expected[4 + cleared..] {
+                *line = "X...";
+            }
+            assert_eq!(board(&engine), expected);
+        }
+    }
+
+    #[test]
+    fn five_line_clear_scores_past_a_tetris() {
+        let mut engine = engine(10, 4);
+        let mut lines = vec!["...."; 10];
+        for line in &mut lines[5..] {
+            *line = ".XXX";
+        }
+        set_board(&mut engine, &lines);
+        // upright I pentomino filling the first cell of the last five lines
+        let def = PieceDef::new("I5", vec![vec![true]; 5], Some(14)).unwrap();
+        engine.current_block = TetrisBlock::from_def(&def, 0, &mut StdRng::seed_from_u64(0), false);
+        engine.current_block.pos = (5, 0);
+
+        assert_eq!(engine.finish_round().cleared, 5);
+        assert_eq!(engine.points, 1500);
+        assert_eq!(engine.clears, [0, 0, 0, 1]);
+        assert!(engine.back_to_back);
+        assert_eq!(board(&engine), ["...."; 10]);
+    }
+
+    #[test]
+    fn strict_clear_waits_for_floating_cells_to_be_supported() {
+        for (strict_clear, cleared) in [(false, 1), (true, 0)] {
+            let mut engine = engine(8, 4);
+            engine.rules.strict_clear = strict_clear;
+            set_board(
+                &mut engine,
+                &[
+                    "....", "....", "....", "....", "....", "..X.", ".X.X", ".XXX",
+                ],
+            );
+            // completes the last line, the cell two lines up floats over a gap
+            place(&mut engine, I, 1, (4, 0));
+            assert_eq!(
+                engine.finish_round().cleared,
+                cleared,
+                "strict {strict_clear}"
+            );
+            if !strict_clear {
+                continue;
+            }
+            assert_eq!(board(&engine)[7], "XXXX");
+
+            // filling the gap supports the cell, the deferred line clears with the new one
+            place(&mut engine, O, 0, (6, 2));
+            engine.current_block.pattern = vec![vec![true]];
+            assert_eq!(engine.finish_round().cleared, 2);
+            assert_eq!(
+                board(&engine),
+                ["....", "....", "....", "....", "....", "....", "X...", "X.X."]
+            );
+        }
+    }
+
+    #[test]
+    fn clears_score_more_at_higher_levels() {
+        for (flat_scoring, level, points) in [(false, 1, 100), (false, 5, 500), (true, 5, 100)] {
+            let rules = Rules {
+                random_rotation: false,
+                flat_scoring,
+                start_level: level,
+                ..Rules::default()
+            };
+            let mut engine = GameEngine::new(rules, 8, 4, 1);
+            set_board(
+                &mut engine,
+                &[
+                    "....", "....", "....", "....", "....", "....", ".XXX", ".XXX",
+                ],
+            );
+            place(&mut engine, I, 1, (4, 0));
+            engine.finish_round();
+
+            assert_eq!(engine.points, points, "level {level}, flat {flat_scoring}");
+        }
+    }
+
+    #[test]
+    fn combo_bonus_grows_with_the_level_unless_scoring_is_flat() {
+        for (flat_scoring, points) in [(false, 500 + 200 + 250), (true, 100 + 40 + 50)] {
+            let rules = Rules {
+                random_rotation: false,
+                flat_scoring,
+                start_level: 5,
+                ..Rules::default()
+            };
+            let mut engine = GameEngine::new(rules, 8, 4, 1);
+            for filled in [2, 1] {
+                let mut lines = vec!["...."; 8];
+                for line in &mut lines[8 - filled..] {
+                    *line = ".XXX";
+                }
+                set_board(&mut engine, &lines);
+                place(&mut engine, I, 1, (4, 0));
+                engine.finish_round();
+            }
+
+            assert_eq!(engine.combo, 1);
+            assert_eq!(engine.points, points, "flat {flat_scoring}");
+        }
+    }
+
+    #[test]
+    fn clear_in_the_middle_moves_only_lines_above_it() {
+        let mut engine = engine(8, 4);
+        set_board(
+            &mut engine,
+            &[
+                "....", "....", "....", "....", "...X", ".XXX", "..X.", ".X..",
+            ],
+        );
+        place(&mut engine, I, 1, (4, 0));
+
+        assert_eq!(engine.finish_round().cleared, 1);
+        assert_eq!(
+            board(&engine),
+            ["....", "....", "....", "....", "....", "X..X", "X.X.", "XX.."]
+        );
+    }
+
+    #[test]
+    fn counts_clears_by_type_and_combo() {
+        let mut engine = engine(8, 4);
+        set_board(
+            &mut engine,
+            &[
+                "....", "....", "....", "....", "....", "....", ".XXX", ".XXX",
+            ],
+        );
+        place(&mut engine, I, 1, (4, 0));
+        engine.finish_round();
+        assert_eq!(
+            (engine.clears, engine.combo, engine.points),
+            ([0, 1, 0, 0], 0, 100)
+        );
+
+        // second clear in a row adds the combo bonus to a single
+        set_board(
+            &mut engine,
+            &[
+                "....", "....", "....", "....", "....", "....", "....", ".XXX",
+            ],
+        );
+        place(&mut engine, I, 1, (4, 0));
+        engine.finish_round();
+        assert_eq!((engine.clears, engine.combo), ([1, 1, 0, 0], 1));
+        assert_eq!(engine.points, 100 + 40 + 50);
+
+        // lock without a clear ends the combo
+        set_board(&mut engine, &["...."; 8]);
+        place(&mut engine, I, 1, (4, 0));
+        engine.finish_round();
+        assert_eq!((engine.combo, engine.points), (-1, 190));
+    }
+
+    #[test]
+    fn ten_lines_raise_the_level_and_speed_up_gravity() {
+        let mut engine = engine(8, 4);
+        let start_interval = engine.move_interval;
+        engine.lines = 8;
+        set_board(
+            &mut engine,
+            &[
+                "....", "....", "....", "....", "....", "....", ".XXX", ".XXX",
+            ],
+        );
+        place(&mut engine, I, 1, (4, 0));
+        engine.finish_round();
+
+        assert_eq!((engine.lines, engine.level), (10, 2));
+        assert_eq!(engine.move_interval, gravity_interval(2));
+        assert!(engine.move_interval < start_interval);
+    }
+
+    #[test]
+    fn reaching_the_marathon_level_wins_the_game() {
+        let rules = Rules {
+            random_rotation: false,
+            mode: GameMode::Marathon { level: 3 },
+            ..Rules::default()
+        };
+        let mut engine = GameEngine::new(rules, 8, 4, 1);
+        let lines = [
+            "....", "....", "....", "....", "....", "....", "....", ".XXX",
+        ];
+
+        // level 2 is on the way, the game goes on
+        engine.lines = 9;
+        set_board(&mut engine, &lines);
+        place(&mut engine, I, 1, (4, 0));
+        engine.finish_round();
+        assert_eq!(engine.level, 2);
+        assert!(!engine.won);
+
+        engine.lines = 19;
+        set_board(&mut engine, &lines);
+        place(&mut engine, I, 1, (4, 0));
+        let result = engine.finish_round();
+
+        assert_eq!(engine.level, 3);
+        assert!(!result.lost);
+        assert!(engine.won);
+        assert_eq!(engine.game_state, GameState::Finished);
+    }
+
+    #[test]
+    fn side_moves_stop_at_the_board_edges() {
+        const O: usize = 1;
+        let mut engine = engine(8, 4);
+        for (pos, direction) in [
+            ((0, 0), MoveDirection::Down),
+            ((0, 2), MoveDirection::Up),
+            ((6, 0), MoveDirection::Down),
+            ((6, 2), MoveDirection::Up),
+        ] {
+            place(&mut engine, O, 0, pos);
+            assert!(!engine.move_side(direction), "{pos:?} {direction:?}");
+            assert_eq!(engine.current_block.pos, pos);
+        }
+
+        // the stack blocks the move just like the edge does
+        set_board(
+            &mut engine,
+            &[
+                "....", "....", "....", "....", "....", "....", "..X.", "....",
+            ],
+        );
+        place(&mut engine, O, 0, (6, 0));
+        assert!(!engine.move_side(MoveDirection::Up));
+        place(&mut engine, O, 0, (4, 0));
+        assert!(engine.move_side(MoveDirection::Up));
+        assert_eq!(engine.current_block.pos, (4, 1));
+    }
+
+    #[test]
+    fn wrapped_pieces_collide_across_the_seam() {
+        let mut engine = engine(8, 4);
+        engine.rules.wrap = true;
+        set_board(
+            &mut engine,
+            &[
+                "....", "....", "....", "....", ".X..", "....", "....", "X...",
+            ],
+        );
+        place(&mut engine, O, 0, (4, 2));
+
+        // half of the piece crosses the edge to the first column
+        assert!(engine.move_side(MoveDirection::Up));
+        assert_eq!(engine.current_block.pos, (4, 3));
+        // the stack on the other side blocks the rest of it
+        assert!(!engine.move_side(MoveDirection::Up));
+        assert_eq!(engine.current_block.pos, (4, 3));
+
+        // and it lands on the cell in the first column
+        assert_eq!(engine.get_end_move_pos(), (5, 3));
+        engine.move_till_end();
+        assert_eq!(
+            board(&engine),
+            ["....", "....", "....", "....", ".X..", "X..X", "X..X", "X..."]
+        );
+    }
+
+    #[test]
+    fn spawn_on_a_blocked_edge_loses_the_game() {
+        const J: usize = 5;
+        for (first_line, lost) in [("X...", false), ("XX..", true)] {
+            let mut engine = engine(8, 4);
+            let mut lines = vec!["...."; 8];
+            lines[0] = first_line;
+            set_board(&mut engine, &lines);
+            place(&mut engine, I, 1, (4, 3));
+            // J is three cells wide along the side axis, so it spawns one cell back from the edge
+            engine.next_blocks[0] =
+                TetrisBlock::from_shape(J, &mut StdRng::seed_from_u64(0), false);
+
+            let result = engine.finish_round();
+
+            assert_eq!(result.lost, lost, "{first_line}");
+            if !lost {
+                assert_eq!(engine.current_block.pos, (0, 1));
+            }
+        }
+    }
+
+    #[test]
+    fn mercy_clears_the_top_of_the_stack_once() {
+        const J: usize = 5;
+        let rules = Rules {
+            random_rotation: false,
+            mercy_count: 1,
+            mercy_lines: 2,
+            ..Rules::default()
+        };
+        let mut engine = GameEngine::new(rules, 8, 4, 1);
+        let mut lines = vec!["...."; 8];
+        lines[0] = "XX..";
+        lines[1] = "XX..";
+        lines[2] = "X...";
+        set_board(&mut engine, &lines);
+        place(&mut engine, I, 1, (4, 3));
+        engine.next_blocks[0] = TetrisBlock::from_shape(J, &mut StdRng::seed_from_u64(0), false);
+
+        let result = engine.finish_round();
+
+        assert!(!result.lost);
+        assert_eq!(engine.mercies_left, 0);
+        assert_eq!(engine.current_block.pos, (0, 1));
+        // the two lines closest to the spawn edge are emptied, the rest stays
+        assert_eq!(&board(&engine)[..3], ["....", "....", "X..."]);
+
+        // with no mercy left the next top out ends the game
+        set_board(&mut engine, &lines);
+        place(&mut engine, I, 1, (4, 3));
+        engine.next_blocks[0] = TetrisBlock::from_shape(J, &mut StdRng::seed_from_u64(0), false);
+        assert!(engine.finish_round().lost);
+        assert_eq!(engine.game_state, GameState::Finished);
+    }
+
+    #[test]
+    fn zen_clears_the_stack_in_the_way_instead_of_losing() {
+        const J: usize = 5;
+        let rules = Rules {
+            random_rotation: false,
+            mode: GameMode::Zen,
+            ..Rules::default()
+        };
+        let mut engine = GameEngine::new(rules, 8, 4, 1);
+        let mut lines = vec!["...."; 8];
+        lines[0] = "XX..";
+        lines[1] = "XX..";
+        set_board(&mut engine, &lines);
+        place(&mut engine, I, 1, (4, 3));
+        engine.next_blocks[0] = TetrisBlock::from_shape(J, &mut StdRng::seed_from_u64(0), false);
+
+        let result = engine.finish_round();
+
+        assert!(!result.lost);
+        assert_eq!(engine.game_state, GameState::Playing);
+        assert_eq!(engine.current_block.pos, (0, 1));
+        // only the line the piece needed is gone
+        assert_eq!(&board(&engine)[..2], ["....", "XX.."]);
+    }
+
+    #[test]
+    fn zen_keeps_the_level_and_hold() {
+        let rules = Rules {
+            random_rotation: false,
+            mode: GameMode::Zen,
+            ..Rules::default()
+        };
+        let mut engine = GameEngine::new(rules, 8, 4, 1);
+        engine.lines = 9;
+        set_board(
+            &mut engine,
+            &[
+                "....", "....", "....", "....", "....", "....", "....", ".XXX",
+            ],
+        );
+        place(&mut engine, I, 1, (4, 0));
+        engine.finish_round();
+        assert_eq!((engine.lines, engine.level), (10, 1));
+        assert!(engine.points > 0);
+
+        assert!(engine.hold());
+        assert!(engine.hold());
+        assert!(!engine.hold_used);
+    }
+
+    #[test]
+    fn spawn_clamp_ignores_empty_trailing_cells() {
+        let mut engine = engine(8, 4);
+        place(&mut engine, I, 1, (4, 3));
+        let mut tall = TetrisBlock::from_shape(I, &mut StdRng::seed_from_u64(0), false);
+        tall.pattern = vec![vec![true, false, false, false], vec![true]];
+        engine.next_blocks[0] = tall;
+
+        assert!(!engine.finish_round().lost);
+        assert_eq!(engine.current_block.pos, (0, 3));
+    }
+
+    #[test]
+    fn t_piece_kicks_down_into_a_well() {
+        const T: usize = 2;
+        let mut engine = engine(9, 5);
+        set_board(
+            &mut engine,
+            &[
+                ".....", ".....", ".....", ".....", ".....", "XX.XX", "X.XXX", "X..XX", "X.XXX",
+            ],
+        );
+        // resting on the overhang it can't turn in place or to a side,
+        // the fourth kick moves it two lines down into the well
+        place(&mut engine, T, 0, (4, 1));
+        engine.rotate90();
+
+        assert_eq!(engine.current_block.orientation, 1);
+        assert_eq!(engine.current_block.pos, (6, 1));
+        assert_eq!(engine.finish_round().cleared, 3);
+        assert_eq!(board(&engine)[6..], [".....", ".....", "XX.XX"]);
+    }
+
+    /// Rotates the current piece, checking it ends up either turned or untouched, inside the board
+    fn rotate_checked(engine: &mut GameEngine) -> bool {
+        let before = engine.current_block.clone();
+        let turned = engine.rotate90();
+        let block = &engine.current_block;
+        assert!(engine.fits(&block.pattern, block.pos), "{:?}", block.pos);
+        if turned {
+            assert_eq!(block.pattern, TetrisBlock::rotate90(&before.pattern));
+            assert_eq!(block.orientation, (before.orientation + 1) % 4);
+        } else {
+            assert_eq!((&block.pattern, block.pos), (&before.pattern, before.pos));
+        }
+        turned
+    }
+
+    #[test]
+    fn i_piece_kicks_off_every_wall() {
+        let mut engine = engine(10, 10);
+        // flat along the side axis, then standing along gravity
+        for (turns, positions) in [
+            (0, [(0, 3), (9, 3), (4, 0), (4, 6)]),
+            (1, [(0, 4), (6, 4), (3, 0), (3, 9)]),
+        ] {
+            for pos in positions {
+                place(&mut engine, I, turns, pos);
+                assert!(rotate_checked(&mut engine), "{turns} {pos:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn i_piece_in_a_narrow_well_stays_put() {
+        let mut engine = engine(8, 4);
+        set_board(&mut engine, &[".XXX"; 8]);
+        place(&mut engine, I, 1, (4, 0));
+        assert!(!rotate_checked(&mut engine));
+    }
+
+    #[test]
+    fn t_piece_kicks_away_from_the_side_wall() {
+        const T: usize = 2;
+        let mut engine = engine(10, 10);
+        // pointing towards the wall it can't turn in place, the next kick moves it one cell back
+        place(&mut engine, T, 3, (4, 8));
+        assert!(rotate_checked(&mut engine));
+        assert_eq!(engine.current_block.pos, (4, 7));
+    }
+
+    #[test]
+    fn t_spin_double_scores_extra() {
+        const T: usize = 2;
+        let mut engine = engine(6, 5);
+        set_board(
+            &mut engine,
+            &[".....", ".....", ".....", "...X.", "X...X", "XX.XX"],
+        );
+        // pointing into the slot under the overhang, turned there as the last action
+        place(&mut engine, T, 0, (4, 1));
+        engine.last_action_was_rotation = true;
+
+        assert_eq!(engine.finish_round().cleared, 2);
+        assert_eq!(engine.points, 1200);
+        assert_eq!(board(&engine)[5], "...X.");
+    }
+
+    /// Middle of the filled cells along the side axis, doubled to stay whole
+    fn doubled_middle(block: &TetrisBlock) -> i32 {
+        let rows: Vec<_> = block
+            .pattern
+            .iter()
+            .flat_map(|col| col.iter().enumerate())
+            .filter(|(_, filled)| **filled)
+            .map(|(j, _)| block.pos.1 + j as i32)
+            .collect();
+        rows.iter().min().unwrap() + rows.iter().max().unwrap() + 1
+    }
+
+    #[test]
+    fn every_shape_spawns_centered() {
+        for height in [9, 10] {
+            let engine = engine(8, height);
+            for shape in 0..SHAPES {
+                let mut block =
+                    TetrisBlock::from_shape(shape, &mut StdRng::seed_from_u64(0), false);
+                for turn in 0..4 {
+                    block.pos = (0, engine.centered_y(&block));
+                    let offset = doubled_middle(&block) - height as i32;
+                    assert!(
+                        offset.abs() <= 2,
+                        "shape {shape} turn {turn} height {height}"
+                    );
+                    block.pattern = TetrisBlock::rotate90(&block.pattern);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn spawn_offset_moves_the_first_piece_within_the_board() {
+        for (spawn_offset, y) in [(0, 3), (2, 5), (-2, 1), (10, 6), (-10, 0)] {
+            let rules = Rules {
+                random_rotation: false,
+                spawn_offset,
+                ..Rules::default()
+            };
+            let engine = GameEngine::new(rules, 8, 10, 1);
+            let block = TetrisBlock::from_shape(I, &mut StdRng::seed_from_u64(0), false);
+            assert_eq!(engine.centered_y(&block), y, "offset {spawn_offset}");
+        }
+    }
+
+    #[test]
+    fn restart_empties_the_board_and_the_score() {
+        let mut engine = engine(8, 4);
+        set_board(
+            &mut engine,
+            &[
+                "....", "....", "....", "....", "....", "X...", ".XXX", ".XXX",
+            ],
+        );
+        place(&mut engine, I, 1, (4, 0));
+        engine.finish_round();
+        assert!(engine.points > 0 && engine.rounds > 0);
+        engine.end_game(false);
+
+        engine.restart(2);
+
+        assert!(engine.filled_area.iter().flatten().all(|c| *c == EMPTY));
+        assert_eq!((engine.points, engine.rounds, engine.lines), (0, 0, 0));
+        assert_eq!(engine.game_state, GameState::Playing);
+        assert_eq!(engine.current_block.pos.0, 0);
+    }
+
+    #[test]
+    fn counts_every_spawned_piece_by_kind() {
+        let mut engine = engine(40, 10);
+        assert_eq!(engine.spawned.iter().sum::<u64>(), 1);
+        for _ in 0..6 {
+            let result = engine.move_till_end().unwrap();
+            assert!(!result.lost);
+        }
+        assert_eq!(engine.rounds, 6);
+        assert_eq!(engine.spawned.iter().sum::<u64>(), engine.rounds + 1);
+
+        engine.undo();
+        assert_eq!(engine.spawned.iter().sum::<u64>(), engine.rounds + 1);
+    }
+
+    #[test]
+    fn history_steps_back_and_forward_until_a_new_lock() {
+        let rules = Rules {
+            random_rotation: false,
+            history_length: 3,
+            ..Rules::default()
+        };
+        let mut engine = GameEngine::new(rules, 40, 10, 1);
+        let mut states = vec![engine.filled_area.clone()];
+        for _ in 0..5 {
+            engine.move_till_end().unwrap();
+            states.push(engine.filled_area.clone());
+        }
+        // only the last 3 locks are kept
+        assert_eq!(engine.history(), (3, 0));
+        while engine.undo() {}
+        assert_eq!(engine.history(), (0, 3));
+        assert_eq!(engine.filled_area, states[2]);
+
+        assert!(engine.redo());
+        assert!(engine.redo());
+        assert_eq!(engine.filled_area, states[4]);
+        assert_eq!(engine.rounds, 4);
+        assert_eq!(engine.history(), (2, 1));
+
+        // locking from an earlier point drops the later ones
+        engine.move_till_end().unwrap();
+        assert_eq!(engine.history(), (3, 0));
+        assert!(!engine.redo());
+    }
+
+    #[test]
+    fn deals_only_pieces_of_a_custom_set() {
+        let pattern = |text: &str| -> Vec<Vec<bool>> {
+            text.split('/')
+                .map(|col| col.chars().map(|c| c == 'X').collect())
+                .collect()
+        };
+        let rules = Rules {
+            pieces: vec![
+                PieceDef::new("P", pattern("XX/XX/XO"), Some(200)).unwrap(),
+                PieceDef::new("I5", pattern("XXXXX"), None).unwrap(),
+            ],
+            random_rotation: false,
+            ..Rules::default()
+        };
+        let mut engine = GameEngine::new(rules, 20, 10, 1);
+        assert_eq!(
+            engine.kinds(),
+            vec![PieceKind::Custom(0), PieceKind::Custom(1)]
+        );
+        for _ in 0..6 {
+            let block = &engine.current_block;
+            assert!(matches!(block.kind, PieceKind::Custom(_)));
+            assert!(engine.fits(&block.pattern, block.pos));
+            if block.kind == PieceKind::Custom(0) {
+                assert_eq!(block.color, 200);
+            }
+            engine.move_till_end();
+        }
+        // the bag deals each piece once before any repeats, 7 pieces are 3 bags and one more
+        let mut counts = engine.spawned[SHAPES..].to_vec();
+        counts.sort_unstable();
+        assert_eq!(counts, [3, 4]);
+    }
+
+    #[test]
+    fn black_blocks_are_not_empty() {
+        let mut engine = engine(8, 4);
+        // palette index 0 is black
+        engine.filled_area[7] = vec![Some(0), Some(0), Some(0), EMPTY];
+        // upright I piece lands on the black cells
+        place(&mut engine, I, 1, (0, 0));
+        assert_eq!(engine.get_end_move_pos(), (3, 0));
+
+        // and fills the gap completing the line
+        place(&mut engine, I, 1, (0, 3));
+        engine.current_block.color = 0;
+        let result = engine.move_till_end().unwrap();
+        assert_eq!(result.cleared, 1);
+        assert_eq!(engine.filled_area[7], vec![EMPTY, EMPTY, EMPTY, Some(0)]);
+    }
+
+    #[test]
+    fn growing_keeps_the_stack_on_the_floor() {
+        let mut engine = engine(4, 3);
+        set_board(&mut engine, &["...", "...", "X..", "XX."]);
+        place(&mut engine, I, 1, (0, 1));
+
+        assert!(!engine.grow(3, 4));
+        assert!(engine.grow(6, 4));
+        assert_eq!(
+            board(&engine),
+            ["....", "....", "....", "....", "X...", "XX.."]
+        );
+        assert_eq!(engine.current_block.pos, (0, 1));
+    }
+
+    #[test]
+    fn cells_keep_the_round_they_were_placed_in() {
+        let mut engine = engine(6, 4);
+        set_board(
+            &mut engine,
+            &["....", "....", "....", "....", "....", "XX.."],
+        );
+        place(&mut engine, O, 0, (2, 1));
+        engine.finish_round();
+        // completes the last line, its upper half falls in its place
+        place(&mut engine, O, 0, (4, 2));
+        engine.finish_round();
+
+        assert_eq!(
+            board(&engine),
+            ["....", "....", "....", ".XX.", ".XX.", "..XX"]
+        );
+        assert_eq!(engine.placed[4][1..3], [0, 0]);
+        assert_eq!(engine.placed[5][2..], [1, 1]);
+    }
+
+    #[test]
+    fn ticks_move_the_piece_until_it_locks() {
+        let mut engine = engine(8, 5);
+        engine.rules.lock_delay = Duration::ZERO;
+        place(&mut engine, I, 0, (0, 0));
+
+        assert_eq!(engine.tick(), None);
+        assert_eq!(engine.current_block.pos, (1, 0));
+        for _ in 0..6 {
+            assert_eq!(engine.tick(), None);
+        }
+        // without lock delay the tick on the floor locks it
+        assert_eq!(engine.current_block.pos, (7, 0));
+        assert!(engine.tick().is_some());
+        assert_eq!(board(&engine)[7], "XXXX.");
+    }
+
+    #[test]
+    fn lock_reset_rules_decide_when_a_resting_piece_locks() {
+        for (lock_reset, locked_at) in [
+            (LockReset::Classic, Some(1)),
+            (LockReset::Step, None),
+            (LockReset::Move, Some(3)),
+        ] {
+            let mut engine = engine(8, 6);
+            engine.rules.lock_delay = Duration::from_millis(500);
+            engine.rules.lock_reset = lock_reset;
+            engine.rules.max_lock_resets = 2;
+            place(&mut engine, O, 0, (6, 2));
+            // landing starts the lock delay
+            assert_eq!(engine.move_forward(), None);
+
+            // the piece slides back and forth on the floor, a move after each 400ms
+            let mut locked = None;
+            for step in 0..5 {
+                if engine.tick_lock(Duration::from_millis(400)).is_some() {
+                    locked = Some(step);
+                    break;
+                }
+                let direction = match step % 2 {
+                    0 => MoveDirection::Up,
+                    _ => MoveDirection::Down,
+                };
+                assert!(engine.move_side(direction));
+            }
+            assert_eq!(locked, locked_at, "{lock_reset}");
+        }
+    }
+
+    #[test]
+    fn hold_is_available_again_after_a_lock() {
+        let mut engine = engine(8, 4);
+        let first = engine.current_block.kind;
+        assert!(!engine.hold_used);
+
+        assert!(engine.hold());
+        assert!(engine.hold_used);
+        // the swapped in piece can't go back before it locks
+        assert!(!engine.hold());
+        assert_eq!(
+            engine.hold_block.as_ref().map(|held| held.kind),
+            Some(first)
+        );
+
+        engine.move_till_end();
+        assert!(!engine.hold_used);
+        assert!(engine.hold());
+        assert_eq!(engine.current_block.kind, first);
+    }
+
+    #[test]
+    fn auto_hold_banks_only_the_chosen_kind() {
+        const T: usize = 2;
+        for (next, held) in [(I, true), (T, false)] {
+            let mut engine = engine(8, 4);
+            engine.rules.auto_hold = Some(PieceKind::I);
+            place(&mut engine, O, 0, (6, 0));
+            engine.next_blocks[0] =
+                TetrisBlock::from_shape(next, &mut StdRng::seed_from_u64(0), false);
+            let after = engine.next_blocks[1].kind;
+
+            engine.finish_round();
+
+            let hold = engine.hold_block.as_ref().map(|block| block.kind);
+            if held {
+                // the I piece went straight to the hold and the one after it took over
+                assert_eq!(hold, Some(PieceKind::I));
+                assert_eq!(engine.current_block.kind, after);
+                assert!(engine.hold_used);
+            } else {
+                assert_eq!(hold, None);
+                assert_eq!(engine.current_block.kind, PieceKind::T);
+                assert!(!engine.hold_used);
+            }
+        }
+
+        // first piece of a game is held too
+        let mut engine = engine(8, 4);
+        let first = engine.current_block.kind;
+        let second = engine.next_blocks[0].kind;
+        engine.rules.auto_hold = Some(first);
+        engine.restart(1);
+        assert_eq!(
+            engine.hold_block.as_ref().map(|block| block.kind),
+            Some(first)
+        );
+        assert_eq!(engine.current_block.kind, second);
+        assert!(engine.hold_used);
+    }
+
+    #[test]
+    fn extra_inputs_are_finesse_faults() {
+        let mut engine = engine(20, 10);
+        engine.apply(Action::MoveUp);
+        engine.apply(Action::MoveDown);
+        let result = engine.apply(Action::HardDrop).unwrap();
+        assert!(result.finesse_fault);
+        assert_eq!(engine.finesse_faults, 1);
+        assert_eq!(engine.piece_inputs, 0);
+
+        engine.apply(Action::MoveUp);
+        let result = engine.apply(Action::HardDrop).unwrap();
+        assert!(!result.finesse_fault);
+
+        // a held key moving the piece to the wall is a single input
+        engine.count_input();
+        while engine.move_side(MoveDirection::Down) {}
+        assert_eq!(engine.finesse_minimum(), 1);
+        let result = engine.apply(Action::HardDrop).unwrap();
+        assert!(!result.finesse_fault);
+        assert_eq!(engine.finesse_faults, 1);
+    }
+
+    #[test]
+    fn score_saturates_instead_of_wrapping() {
+        let mut engine = engine(8, 4);
+        set_board(
+            &mut engine,
+            &[
+                "....", "....", "....", "....", "....", "....", "....", ".XXX",
+            ],
+        );
+        engine.points = u64::MAX - 10;
+        engine.rounds = u64::MAX;
+        place(&mut engine, I, 1, (4, 0));
+
+        assert_eq!(engine.finish_round().cleared, 1);
+        assert_eq!(engine.points, u64::MAX);
+        assert_eq!(engine.rounds, u64::MAX);
+    }
+
+    #[test]
+    fn gravity_waits_for_the_entry_delay() {
+        let mut engine = engine(8, 5);
+        engine.rules.entry_delay = Duration::from_millis(300);
+        engine.move_interval = Duration::from_millis(100);
+        let next = engine.next_block().kind;
+        engine.move_till_end();
+
+        for _ in 0..2 {
+            assert_eq!(engine.tick(), None);
+            assert!(engine.is_entering());
+            assert_ne!(engine.current_block.pos.0, 0);
+        }
+        assert_eq!(engine.apply(Action::MoveUp), None);
+
+        engine.tick();
+        assert!(!engine.is_entering());
+        assert_eq!(engine.current_block.kind, next);
+        assert_eq!(engine.current_block.pos.0, 0);
+        engine.tick();
+        assert_eq!(engine.current_block.pos.0, 1);
+    }
+
+    #[test]
+    fn height_profile_and_holes_of_a_built_board() {
+        let mut engine = engine(6, 4);
+        assert_eq!(engine.height_profile(), [0, 0, 0, 0]);
+        assert_eq!(engine.holes(), 0);
+
+        set_board(
+            &mut engine,
+            &["....", "....", ".X..", "XX..", "X..X", "XXX."],
+        );
+        // heights count from the floor to the filled cell closest to the spawn edge
+        assert_eq!(engine.height_profile(), [3, 4, 1, 2]);
+        // the gap under the overhang in the second column and the one under the last cell
+        assert_eq!(engine.holes(), 2);
+
+        // a single cell at the spawn edge covers the whole column beneath it
+        set_board(
+            &mut engine,
+            &["..X.", "....", "....", "....", "....", "...."],
+        );
+        assert_eq!(engine.height_profile(), [0, 0, 6, 0]);
+        assert_eq!(engine.holes(), 5);
+    }
+
+    #[test]
+    fn soft_drop_scores_a_point_per_cell() {
+        let mut engine = engine(8, 4);
+        place(&mut engine, I, 1, (0, 0));
+        let end = engine.get_end_move_pos().0;
+        assert!(end > 0);
+
+        for _ in 0..end {
+            assert!(engine.soft_drop().is_none());
+        }
+        assert_eq!(engine.points, end as u64);
+        // resting on the floor it only starts the lock delay
+        assert!(engine.soft_drop().is_none());
+        assert_eq!(engine.points, end as u64);
+        assert_eq!(engine.current_block.pos.0, end);
+    }
+}
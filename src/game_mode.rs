@@ -0,0 +1,79 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+/// Goal of the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    /// Play until the stack reaches the spawn edge
+    Endless,
+    /// Clear given number of lines as fast as possible
+    Sprint { lines: u32 },
+    /// Score as many points as possible in given time
+    Ultra { duration: Duration },
+    /// Survive garbage lines rising from the far edge of the board
+    Garbage,
+    /// Play until given level is reached
+    Marathon { level: u64 },
+    /// Build without pressure, the game never ends and hold is always available
+    Zen,
+}
+
+impl GameMode {
+    /// Sprint or marathon goal reached after clearing `lines` in total and getting to `level`
+    pub fn is_complete(self, lines: u64, level: u64) -> bool {
+        match self {
+            GameMode::Sprint { lines: goal } => lines >= goal as u64,
+            GameMode::Marathon { level: goal } => level >= goal,
+            _ => false,
+        }
+    }
+
+    /// Remaining time of an ultra game after playing for `time`
+    pub fn time_left(self, time: Duration) -> Option<Duration> {
+        match self {
+            GameMode::Ultra { duration } => Some(duration.saturating_sub(time)),
+            _ => None,
+        }
+    }
+}
+
+/// Time as `mm:ss.cc`
+pub fn format_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    format!(
+        "{:02}:{:02}.{:02}",
+        secs / 60,
+        secs % 60,
+        time.subsec_millis() / 10
+    )
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "endless" => Ok(GameMode::Endless),
+            "garbage" => Ok(GameMode::Garbage),
+            "marathon" => Ok(GameMode::Marathon { level: 15 }),
+            "zen" => Ok(GameMode::Zen),
+            "sprint" => Ok(GameMode::Sprint { lines: 40 }),
+            "ultra" => Ok(GameMode::Ultra {
+                duration: Duration::from_secs(120),
+            }),
+            _ => Err(format!("unknown game mode {s}")),
+        }
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GameMode::Endless => "endless",
+            GameMode::Sprint { .. } => "sprint",
+            GameMode::Ultra { .. } => "ultra",
+            GameMode::Garbage => "garbage",
+            GameMode::Marathon { .. } => "marathon",
+            GameMode::Zen => "zen",
+        })
+    }
+}
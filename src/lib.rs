@@ -0,0 +1,8 @@
+//! Game logic of terminal tetris without any terminal handling,
+//! for bots and other frontends
+
+pub mod engine;
+pub mod game_mode;
+pub mod piece_bag;
+pub mod snapshot;
+pub mod tetris_block;
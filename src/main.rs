@@ -1,28 +1,401 @@
+use clap::{error::ErrorKind, value_parser, CommandFactory, Parser};
+use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+};
 use ratatui::layout::Rect;
-use std::io::{self};
-use tetris::Tetris;
+use std::{
+    env,
+    io::{self},
+    iter,
+    path::PathBuf,
+    process,
+};
+use tetris::{
+    board_file,
+    config::{Config, Orientation, Playfield},
+    key_bindings::KeyBindings,
+    pieces,
+    replay::{Follower, Recorder},
+    theme::{Theme, ThemePreset},
+    Tetris,
+};
+use tetris_engine::{game_mode::GameMode, snapshot::Snapshot};
 
 mod tetris;
 
+/// Tetris in the terminal, options not given here come from the config file
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// Redraw only what changed, for slow connections
+    #[arg(long)]
+    quiet: bool,
+    /// Single status line under the board instead of the panels
+    #[arg(long)]
+    compact: bool,
+    /// Print the result of the game after it closes
+    #[arg(long)]
+    summary: bool,
+    /// Play the daily challenge
+    #[arg(long)]
+    daily: bool,
+    /// Practice mode, the board can be emptied keeping the score
+    #[arg(long)]
+    practice: bool,
+    /// Pieces moved off one side of the board appear on the other
+    #[arg(long)]
+    wrap: bool,
+    /// Locked blocks fade into the board background
+    #[arg(long)]
+    fading: bool,
+    /// A bot plays until a key is pressed
+    #[arg(long)]
+    attract: bool,
+    /// Pieces fall down instead of to the right
+    #[arg(long)]
+    vertical: bool,
+    /// "fit" board fills the terminal, "standard" one is 10 by 20 cells
+    #[arg(long)]
+    playfield: Option<Playfield>,
+    /// "endless", "sprint", "ultra", "garbage", "marathon" or "zen"
+    #[arg(long)]
+    mode: Option<GameMode>,
+    /// "classic", "high-contrast", "random" or "color-blind" piece colors
+    #[arg(long)]
+    theme: Option<ThemePreset>,
+    /// Deal the same pieces every game
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Board width in cells, instead of filling the terminal
+    #[arg(long, value_name = "CELLS")]
+    width: Option<u16>,
+    /// Board height in cells, even as a terminal row shows 2 of them
+    #[arg(long, value_name = "CELLS")]
+    height: Option<u16>,
+    /// Level the game starts at
+    #[arg(long, value_name = "LEVEL", value_parser = value_parser!(u64).range(1..))]
+    start_level: Option<u64>,
+    /// Continue a saved game, it brings its own board
+    #[arg(long, value_name = "PATH")]
+    load: Option<PathBuf>,
+    /// Start every game with the stack read from the file
+    #[arg(long, value_name = "PATH", conflicts_with = "load")]
+    board: Option<PathBuf>,
+    /// Write a replay of the game to the file
+    #[arg(long, value_name = "PATH")]
+    record: Option<PathBuf>,
+    /// Play the replay being written to the file, the game is set up the way it was recorded
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["seed", "width", "height", "record", "load", "board"]
+    )]
+    watch: Option<PathBuf>,
+    /// Write the config with all options to the file and quit
+    #[arg(long, value_name = "PATH")]
+    dump_config: Option<PathBuf>,
+}
+
+impl Args {
+    /// Sets the options given as arguments over the ones from the config file
+    fn apply(&self, config: &mut Config) {
+        config.quiet |= self.quiet;
+        config.compact_hud |= self.compact;
+        config.daily |= self.daily;
+        config.practice |= self.practice;
+        config.wrap |= self.wrap;
+        config.fading |= self.fading;
+        config.attract |= self.attract;
+        if self.vertical {
+            config.orientation = Orientation::Vertical;
+        }
+        if let Some(playfield) = self.playfield {
+            config.playfield = playfield;
+        }
+        if let Some(mode) = self.mode {
+            config.mode = mode;
+        }
+        if let Some(theme) = self.theme {
+            config.theme = theme;
+        }
+        if let Some(start_level) = self.start_level {
+            config.start_level = start_level;
+        }
+    }
+}
+
+/// Arguments after the program name, applied to the config. Board sizes the config can't fit are errors
+fn parse_args(
+    args: impl IntoIterator<Item = String>,
+    config: &mut Config,
+) -> Result<Args, clap::Error> {
+    let args = Args::try_parse_from(iter::once("tetris".to_string()).chain(args))?;
+    args.apply(config);
+
+    let error = |kind, message: String| Args::command().error(kind, message);
+    if config.playfield == Playfield::Standard && (args.width.is_some() || args.height.is_some()) {
+        return Err(error(
+            ErrorKind::ArgumentConflict,
+            "--width and --height can't be used with a standard playfield".to_string(),
+        ));
+    }
+
+    // board size is given in cells, it's drawn with a border around it
+    // and a terminal row fits two cells across gravity
+    let (min_width, min_height) = Tetris::min_size(config);
+    let side_width = Tetris::side_width(config);
+    if let Some(width) = args.width {
+        if width.saturating_add(2 + side_width) < min_width {
+            return Err(error(
+                ErrorKind::ValueValidation,
+                format!(
+                    "width {width} is too small, panels below the board need at least {}",
+                    min_width - 2 - side_width
+                ),
+            ));
+        }
+    }
+    if let Some(height) = args.height {
+        if height % 2 == 1 {
+            return Err(error(
+                ErrorKind::ValueValidation,
+                format!("height {height} has to be even, a terminal row shows 2 cells"),
+            ));
+        }
+        // the status line of the compact HUD is the least room under the board
+        if height / 2 + 3 < min_height {
+            return Err(error(
+                ErrorKind::ValueValidation,
+                format!(
+                    "height {height} is too small, it has to be at least {}",
+                    (min_height - 3) * 2
+                ),
+            ));
+        }
+    }
+    Ok(args)
+}
+
 fn main() -> io::Result<()> {
-    let terminal = ratatui::init();
+    let mut file_config = Config::load();
+    let mut config = file_config.clone();
+    let args = parse_args(env::args().skip(1), &mut config).unwrap_or_else(|error| error.exit());
+
+    if let Some(path) = args.dump_config {
+        if let Err(error) = config.save_to(&path) {
+            eprintln!("Can't write config to {}: {error}", path.display());
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // watched game is set up the way it was recorded, timers and demos are left to the replay
+    let watched = args.watch.map(|path| {
+        let mut follower = Follower::open(&path).unwrap_or_else(|error| {
+            eprintln!("Can't read replay {}: {error}", path.display());
+            process::exit(1);
+        });
+        eprintln!("Waiting for the game in {}", path.display());
+        let header = follower.header().unwrap_or_else(|error| {
+            eprintln!("Invalid replay: {error}");
+            process::exit(2);
+        });
+        config = Config {
+            shown_help_before: true,
+            countdown: false,
+            attract: false,
+            idle_demo_ms: 0,
+            ..header.config.clone()
+        };
+        (follower, header)
+    });
+    let watched_board = watched
+        .as_ref()
+        .filter(|_| config.playfield == Playfield::Fit)
+        .map(|(_, header)| {
+            let (along, across) = (header.board.0 as u16, header.board.1 as u16);
+            match config.orientation {
+                Orientation::Horizontal => (along, across),
+                Orientation::Vertical => (across, along),
+            }
+        });
+
+    // saved game brings its board size, a terminal too small for it shows the usual message
+    let snapshot = args.load.map(|path| {
+        Snapshot::load(&path).unwrap_or_else(|error| {
+            eprintln!("Invalid save: {error}");
+            process::exit(2);
+        })
+    });
+    let (width, height) = match (&snapshot, watched_board) {
+        (Some(snapshot), _) => (Some(snapshot.width), Some(snapshot.height)),
+        (None, Some((width, height))) => (Some(width), Some(height)),
+        (None, None) => (args.width, args.height),
+    };
+
+    let board = match &watched {
+        Some((_, header)) => (!header.stack.is_empty()).then(|| header.stack.clone()),
+        None => args.board.map(|path| {
+            board_file::load(&path, &Theme::from_config(&config)).unwrap_or_else(|error| {
+                eprintln!("Invalid board: {error}");
+                process::exit(2);
+            })
+        }),
+    };
+
+    let keys = KeyBindings::load(config.orientation).unwrap_or_else(|error| {
+        eprintln!("Invalid key bindings: {error}");
+        process::exit(2);
+    });
+    let pieces = match &watched {
+        Some((_, header)) => header.pieces.clone(),
+        None => pieces::load().unwrap_or_else(|error| {
+            eprintln!("Invalid pieces: {error}");
+            process::exit(2);
+        }),
+    };
+
+    // opened before taking over the terminal, so errors are printed normally
+    let recorder = args.record.map(|path| {
+        Recorder::create(&path).unwrap_or_else(|error| {
+            eprintln!("Can't write replay to {}: {error}", path.display());
+            process::exit(1);
+        })
+    });
+
+    let mut terminal = ratatui::init();
     let size = terminal.size().unwrap_or(ratatui::layout::Size {
         width: 40,
         height: 30,
     });
-    let app = Tetris::new(
-        Rect {
-            width: size.width,
-            height: size.height,
-            x: 0,
-            y: 0,
-        },
-        terminal,
-    );
-    let app_result = app.run();
+    let screen_rect = Rect {
+        width: width.map_or(size.width, |width| {
+            width.saturating_add(2 + Tetris::side_width(&config))
+        }),
+        height: height.map_or(size.height, |height| {
+            Tetris::screen_height(&config, height / 2)
+        }),
+        x: 0,
+        y: 0,
+    };
+    let seed = args
+        .seed
+        .or(watched.as_ref().map(|(_, header)| header.seed));
+    let mut app = match seed {
+        Some(seed) => Tetris::new_with_seed(screen_rect, config, keys, seed),
+        None => Tetris::new(screen_rect, config, keys),
+    };
+    if !pieces.is_empty() {
+        if let Err(error) = app.set_pieces(pieces) {
+            ratatui::restore();
+            eprintln!("Invalid pieces: {error}");
+            process::exit(2);
+        }
+    }
+    // a board file has to keep matching the board
+    if width.is_none() && height.is_none() && board.is_none() {
+        app.grow_with_terminal();
+    }
+    if let Some(board) = board {
+        if let Err(error) = app.set_board(board) {
+            ratatui::restore();
+            eprintln!("Invalid board: {error}");
+            process::exit(2);
+        }
+    }
+    if let Some(snapshot) = snapshot {
+        if let Err(error) = app.restore(snapshot) {
+            ratatui::restore();
+            eprintln!("Can't load save: {error}");
+            process::exit(2);
+        }
+    }
+    if let Some((follower, _)) = watched {
+        app.watch(follower);
+    }
+    if let Some(recorder) = recorder {
+        if let Err(error) = app.record(recorder) {
+            ratatui::restore();
+            eprintln!("Can't write replay: {error}");
+            process::exit(1);
+        }
+    }
+    if !file_config.shown_help_before {
+        file_config.shown_help_before = true;
+        let _ = file_config.save();
+    }
+    // clicks and scrolling control the piece, see `Tetris::handle_mouse_event`
+    let _ = execute!(io::stdout(), EnableMouseCapture);
+    let app_result = app.run(&mut terminal);
+    let _ = execute!(io::stdout(), DisableMouseCapture);
+    // printed to the normal screen, the alternate one is gone by now
     ratatui::restore();
-    if let Err(ref error) = app_result {
-        println!("{error}")
+    match app_result {
+        Ok(ref summary) if args.summary => println!("{summary}"),
+        Ok(_) => {}
+        Err(ref error) => println!("{error}"),
+    }
+    app_result.map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, clap::Error> {
+        parse_args(
+            args.iter().map(|arg| arg.to_string()),
+            &mut Config::default(),
+        )
+    }
+
+    #[test]
+    fn board_size_out_of_range_is_an_error() {
+        let (min_width, min_height) = Tetris::min_size(&Config::default());
+        let min_width = min_width - 2 - Tetris::side_width(&Config::default());
+        let min_height = (min_height - 3) * 2;
+
+        let width = parse(&["--width", &min_width.to_string()]).unwrap().width;
+        assert_eq!(width, Some(min_width));
+        let error = parse(&["--width", &(min_width - 1).to_string()])
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("too small"), "{error}");
+
+        assert!(parse(&["--height", &min_height.to_string()]).is_ok());
+        assert!(parse(&["--height", &(min_height - 2).to_string()]).is_err());
+        assert!(parse(&["--height", &(min_height + 1).to_string()]).is_err());
+    }
+
+    #[test]
+    fn board_size_needs_a_fitted_playfield() {
+        let error = parse(&["--playfield", "standard", "--width", "40"])
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("standard playfield"), "{error}");
+        assert!(parse(&["--height", "20", "--playfield", "standard"]).is_err());
+        assert!(parse(&["--playfield", "standard"]).is_ok());
+    }
+
+    #[test]
+    fn malformed_arguments_are_errors() {
+        assert!(parse(&["--width"]).is_err());
+        assert!(parse(&["--width", "wide"]).is_err());
+        assert!(parse(&["--start-level", "0"]).is_err());
+        assert!(parse(&["--load", "a", "--board", "b"]).is_err());
+        assert!(parse(&["--bogus"]).is_err());
+        assert!(parse(&["--watch", "a", "--seed", "1"]).is_err());
+        assert!(parse(&["--watch", "a"]).is_ok());
+    }
+
+    #[test]
+    fn help_lists_the_arguments() {
+        let error = parse(&["--help"]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::DisplayHelp);
+        let help = error.to_string();
+        for argument in ["--start-level <LEVEL>", "--playfield", "--watch <PATH>"] {
+            assert!(help.contains(argument), "{help}");
+        }
     }
-    app_result
 }
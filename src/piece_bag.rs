@@ -0,0 +1,79 @@
+use rand::{seq::SliceRandom, Rng};
+use std::{fmt, str::FromStr};
+
+/// How next piece shape is picked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Randomizer {
+    /// Every shape once in shuffled order, then again
+    Bag,
+    /// Every piece independently random
+    Classic,
+}
+
+/// Hands out shapes, in bag mode each of them once before any repeats
+#[derive(Debug, Clone)]
+pub struct PieceBag {
+    randomizer: Randomizer,
+    /// Number of shapes to pick from, `0..count`
+    count: usize,
+    shapes: Vec<usize>,
+}
+
+impl PieceBag {
+    pub fn new(randomizer: Randomizer, count: usize) -> Self {
+        Self {
+            randomizer,
+            count: count.max(1),
+            shapes: Vec::with_capacity(count),
+        }
+    }
+
+    pub fn next_shape(&mut self, rng: &mut impl Rng) -> usize {
+        if self.randomizer == Randomizer::Classic {
+            return rng.gen_range(0..self.count);
+        }
+        if self.shapes.is_empty() {
+            self.shapes.extend(0..self.count);
+            self.shapes.shuffle(rng);
+        }
+        self.shapes.pop().unwrap()
+    }
+}
+
+impl FromStr for Randomizer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bag" => Ok(Randomizer::Bag),
+            "classic" => Ok(Randomizer::Classic),
+            _ => Err(format!("unknown randomizer {s}")),
+        }
+    }
+}
+
+impl fmt::Display for Randomizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Randomizer::Bag => "bag",
+            Randomizer::Classic => "classic",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn every_bag_deals_each_shape_once() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut bag = PieceBag::new(Randomizer::Bag, 7);
+        for _ in 0..10 {
+            let mut shapes: Vec<usize> = (0..7).map(|_| bag.next_shape(&mut rng)).collect();
+            shapes.sort_unstable();
+            assert_eq!(shapes, [0, 1, 2, 3, 4, 5, 6]);
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use crate::{engine::GameState, tetris_block::TetrisBlock};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path, time::Duration};
+
+/// Saved game, written as JSON with the board as one array of cells per board line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Board size in cells, as given with `--width` and `--height`
+    pub width: u16,
+    pub height: u16,
+    pub seed: u64,
+    pub points: u64,
+    pub rounds: u64,
+    pub lines: u64,
+    pub level: u64,
+    pub game_state: GameState,
+    pub move_interval: Duration,
+    /// Palette index of every cell, `null` where it's empty
+    pub filled_area: Vec<Vec<Option<u8>>>,
+    pub current_block: TetrisBlock,
+    pub next_blocks: Vec<TetrisBlock>,
+    pub hold_block: Option<TetrisBlock>,
+}
+
+impl Snapshot {
+    pub fn save_to(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| format!("Can't read {}: {error}", path.display()))?;
+        Self::from_json(&text).map_err(|error| format!("{}: {error}", path.display()))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("snapshot has only serializable fields")
+    }
+
+    /// Reads back what `to_json` wrote, every field has to be present and valid
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let snapshot: Self = serde_json::from_str(text).map_err(|error| error.to_string())?;
+        if snapshot.next_blocks.is_empty() {
+            return Err("missing next".to_string());
+        }
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{GameEngine, Rules};
+
+    #[test]
+    fn saved_game_reads_back_the_same() {
+        let mut engine = GameEngine::new(Rules::default(), 10, 6, 7);
+        engine.move_till_end();
+        engine.hold();
+        let snapshot = engine.snapshot();
+
+        let loaded = Snapshot::from_json(&snapshot.to_json()).unwrap();
+        assert_eq!(loaded.to_json(), snapshot.to_json());
+        assert_eq!(loaded.filled_area, engine.filled_area);
+        assert_eq!(loaded.current_block.pattern, engine.current_block.pattern);
+        assert_eq!(loaded.current_block.color, engine.current_block.color);
+        assert!(loaded.hold_block.is_some());
+
+        assert!(Snapshot::from_json("{}").is_err());
+    }
+}
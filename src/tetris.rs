@@ -1,581 +1,2864 @@
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ai::Plan;
+use color::{blend, darken, glyph, ColorMode};
+use config::{Config, HoldIndicator, InputMode, NextPanel, Orientation, Playfield};
+use crossterm::{
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        MouseButton, MouseEvent, MouseEventKind, PopKeyboardEnhancementFlags,
+        PushKeyboardEnhancementFlags,
+    },
+    execute, terminal,
+};
+use daily::Daily;
+use key_bindings::{key_name, Action, KeyBindings};
 use ratatui::{
     buffer::Buffer,
-    layout::{Alignment, Rect},
-    style::{Color, Style, Stylize},
+    layout::{Alignment, Position, Rect},
+    style::{Color, Modifier, Style, Stylize},
     text::{self, Text},
     widgets::{
-        canvas::{Canvas, Painter},
-        Block, Paragraph, Widget,
+        canvas::{Canvas, Context, Painter, Shape},
+        Block, Clear, Gauge, Paragraph, Widget,
     },
     DefaultTerminal, Frame,
 };
+use replay::{Follower, Recorder};
 use std::{
-    io,
-    sync::{Arc, Mutex},
-    thread,
-    time::Duration,
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt, fs,
+    io::{self, Write},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
-use tetris_block::TetrisBlock;
-
-pub mod tetris_block;
-
-#[derive(Debug, PartialEq, Eq)]
-pub enum GameState {
-    Playing,
-    Paused,
-    Finished,
-}
+use tetris_engine::{
+    engine::{GameEngine, GameState, LockResult, MoveDirection, EMPTY, LINES_PER_LEVEL},
+    game_mode::{format_time, GameMode},
+    snapshot::Snapshot,
+    tetris_block::{PieceDef, PieceKind, TetrisBlock},
+};
+use theme::Theme;
+
+pub mod ai;
+pub mod board_file;
+pub mod color;
+pub mod config;
+pub mod daily;
+#[cfg(feature = "debug-overlay")]
+mod debug_overlay;
+pub mod key_bindings;
+pub mod pieces;
+pub mod replay;
+pub mod theme;
 
 #[derive(Debug)]
 pub struct Tetris {
     cursor_state: bool,
+    dirty: bool,
     locked: bool,
-    game_state: GameState,
-    rounds: u64,
-    points: u64,
+    show_help: bool,
+    show_ghost: bool,
+    /// Gravity moves the piece forward, without it pieces move only on input
+    gravity_enabled: bool,
+    /// Grid overlay with the lanes of the falling piece highlighted
+    show_grid: bool,
+    /// Bright outline on the empty cells around the falling piece
+    show_outline: bool,
+    pause_selection: usize,
+    /// Quit was asked for and waits for confirmation, the game stands still meanwhile
+    pending_quit: bool,
+    countdown_elapsed: Duration,
+    sound_enabled: bool,
+    /// Board drawn twice as large when the terminal fits it
+    big: bool,
+    /// Size of board cells in the current layout, 2 in big mode
+    scale: u16,
+    /// Board grows when the terminal does
+    grow_with_terminal: bool,
+    /// Terminal reports key releases, needed for auto repeat
+    key_releases: bool,
+    /// Direction held down and when it moves the piece next
+    held_move: Option<(MoveDirection, Instant)>,
+    /// Moves and rotations made during the countdown or a line clear, applied once play resumes
+    buffered: VecDeque<Action>,
+    /// Soft drop key is held, speeding up gravity until it's released or the piece locks
+    soft_dropping: bool,
+    /// Play time of each lock, by the round its cells were placed in, for fading blocks.
+    /// Only rounds that still have cells on the board are kept
+    lock_times: HashMap<u64, Duration>,
+    engine: GameEngine,
+    start_time: Instant,
+    paused_time: Duration,
+    finished_at: Option<Instant>,
+    /// Time the piece has been falling since the last gravity step
+    gravity_elapsed: Duration,
+    /// When the last frame was drawn, for the refresh rate cap
+    last_frame: Option<Instant>,
     exit: bool,
-    reset: bool,
     screen_rect: Rect,
     board_rect: Rect,
+    /// Room for the board, a standard one leaves margins in it
+    field_rect: Rect,
     info_rect: Vec<Rect>,
     next_rect: Rect,
-    game_width: usize,
-    game_height: usize,
-    next_width: i32,
-    next_height: i32,
-    filled_area: Vec<Vec<Color>>,
-    current_block: TetrisBlock,
-    next_block: TetrisBlock,
-    terminal: Arc<Mutex<DefaultTerminal>>,
-    move_interval: Duration,
+    hold_rect: Rect,
+    /// Status line replaces the panels under the board
+    compact: bool,
+    config: Config,
+    keys: KeyBindings,
+    status: Option<String>,
+    input_echo: Option<(&'static str, Instant)>,
+    /// Key event log toggled with `F12`
+    #[cfg(feature = "debug-overlay")]
+    debug: debug_overlay::DebugOverlay,
+    /// When the last piece placed with too many inputs locked
+    finesse_fault_at: Option<Instant>,
+    /// Stack was close to the spawn edge after the last lock, the bell rings once on getting there
+    danger_warned: bool,
+    last_key: Option<(KeyCode, Instant)>,
+    /// Direction and time of the last move key press, for double taps
+    last_tap: Option<(MoveDirection, Instant)>,
+    attract: bool,
+    /// Last key or mouse input, the demo starts after `idle_demo_ms` without any on the game over screen
+    last_input: Instant,
+    bot_plan: Option<Plan>,
+    color_mode: ColorMode,
+    theme: Theme,
+    daily: Option<Daily>,
+    fixed_seed: bool,
+    /// Stack every game starts with, from `--board`
+    setup: Option<Vec<Vec<Option<u8>>>>,
+    recorder: Option<Recorder>,
+    /// Replay being watched, its events play the game instead of the keys
+    follower: Option<Follower>,
+    /// Time into the watched game its last event was played at
+    replay_time: Duration,
 }
 
-pub enum MoveDirection {
-    Up,
-    Down,
+/// Options of the pause menu
+const PAUSE_MENU: [&str; 3] = ["Resume", "Restart", "Quit"];
+
+/// Shown for a second each before the game starts
+const COUNTDOWN: [&str; 4] = ["3", "2", "1", "Go"];
+
+/// Cells between lines of the grid overlay
+const GRID_SPACING: usize = 4;
+
+/// Longest wait for input before timers are advanced
+const FRAME: Duration = Duration::from_millis(50);
+
+/// How long the finesse fault count stays highlighted after a fault
+const FINESSE_FLASH: Duration = Duration::from_secs(1);
+
+/// Most gravity time made up in one update, a longer stall like a suspended terminal
+/// doesn't drop the piece on its own
+const MAX_CATCH_UP: Duration = Duration::from_millis(250);
+
+fn move_action(direction: MoveDirection) -> Action {
+    match direction {
+        MoveDirection::Up => Action::MoveUp,
+        MoveDirection::Down => Action::MoveDown,
+    }
 }
 
-impl Tetris {
-    pub fn new(mut screen_rect: Rect, terminal: DefaultTerminal) -> Self {
-        if screen_rect.height < 10 {
-            screen_rect.height = 10;
-        }
+/// Number with a metric suffix and a decimal below 100, e.g. `1.2M` for 1 234 567
+fn abbreviate(number: u64) -> String {
+    const UNITS: [&str; 7] = ["", "k", "M", "G", "T", "P", "E"];
+    let mut unit = 0;
+    let mut whole = number;
+    let mut tenths = 0;
+    while whole >= 1000 {
+        tenths = whole % 1000 / 100;
+        whole /= 1000;
+        unit += 1;
+    }
+    if unit > 0 && whole < 100 {
+        format!("{whole}.{tenths}{}", UNITS[unit])
+    } else {
+        format!("{whole}{}", UNITS[unit])
+    }
+}
 
-        if screen_rect.width < 40 {
-            screen_rect.width = 40;
-        }
+/// Width of the hold and info panels under the board
+const FOOTER_WIDTH: u16 = 46;
+/// Width of the next panel beside the board
+const NEXT_COLUMN_WIDTH: u16 = 11;
+/// Height of the panels under the board
+const FOOTER_HEIGHT: u16 = 5;
+/// Shorter screens get the compact HUD, a single line under the board
+const COMPACT_BELOW: u16 = 16;
+
+/// Places of the panels on the screen, the board takes what's left above the ones at the bottom
+/// and beside the next column
+struct Layout {
+    /// Panels under the board collapsed into a single status line
+    compact: bool,
+    screen: Rect,
+    board: Rect,
+    /// Room for the board, a standard one is put in its middle
+    field: Rect,
+    next: Rect,
+    hold: Rect,
+    info: Vec<Rect>,
+}
 
-        let footer_height = 5;
+impl Layout {
+    /// Fits the panels into the screen, recomputed whenever the screen grows
+    fn new(mut screen_rect: Rect, config: &Config) -> Self {
+        let (min_width, min_height) = Tetris::min_size(config);
+        screen_rect.width = screen_rect.width.max(min_width);
+        screen_rect.height = screen_rect.height.max(min_height);
+
+        // a standard board too tall to fit above the panels gets the single status line instead
+        let board_rows = config
+            .playfield
+            .size(config.orientation)
+            .map_or(0, |(_, cells_height)| cells_height / 2 + 2);
+        let compact = config.compact_hud
+            || screen_rect.height < COMPACT_BELOW
+            || screen_rect.height < board_rows + FOOTER_HEIGHT;
+        let footer_height = Layout::footer_height(compact);
+        let footer_y = screen_rect.y + screen_rect.height - footer_height;
+
+        let next_rect = Layout::next_rect(screen_rect, compact, config);
+        let (board_width, footer_x) = match config.next_panel {
+            NextPanel::Strip => (screen_rect.width, next_rect.x + next_rect.width),
+            NextPanel::Column => (screen_rect.width - next_rect.width, screen_rect.x),
+        };
 
-        let board_rect = Rect {
+        let field_rect = Rect {
             x: screen_rect.x,
             y: screen_rect.y,
-            width: screen_rect.width,
+            width: board_width,
             height: screen_rect.height - footer_height,
         };
+        let board_rect = match config.playfield.size(config.orientation) {
+            Some((cells_width, cells_height)) => {
+                let (width, height) = (cells_width + 2, cells_height / 2 + 2);
+                Rect {
+                    x: field_rect.x + (field_rect.width - width) / 2,
+                    y: field_rect.y + (field_rect.height - height) / 2,
+                    width,
+                    height,
+                }
+            }
+            None => field_rect,
+        };
 
-        let next_rect = Rect {
-            x: board_rect.x,
-            y: board_rect.height,
+        if compact {
+            // upcoming pieces in a strip go to the status line too
+            return Self {
+                compact,
+                screen: screen_rect,
+                board: board_rect,
+                field: field_rect,
+                next: next_rect,
+                hold: Rect::default(),
+                info: vec![Rect {
+                    x: screen_rect.x,
+                    y: footer_y,
+                    width: screen_rect.width,
+                    height: footer_height,
+                }],
+            };
+        }
+
+        let hold_rect = Rect {
+            x: footer_x,
+            y: footer_y,
             width: 11,
             height: footer_height,
         };
 
-        let info_rect = vec![Rect {
-            x: next_rect.x + next_rect.width,
-            y: next_rect.y,
-            width: 29,
-            height: next_rect.height,
+        let mut info_rect = vec![Rect {
+            x: hold_rect.x + hold_rect.width,
+            y: footer_y,
+            width: 35,
+            height: footer_height,
         }];
 
-        let game_width = (board_rect.width - 2) as usize;
-        let game_height = (board_rect.height - 2) as usize * 2;
-        let next_width: i32 = (next_rect.width - 2) as i32;
-        let next_height = (next_rect.height - 2) as i32 * 2;
-
-        let filled_area = vec![vec![Color::Black; game_height]; game_width];
-
-        let mut current_block = TetrisBlock::new_random();
-        let mut next_block = TetrisBlock::new_random();
-        current_block.pos = (
-            0,
-            game_height as i32 / 2 - current_block.pattern[0].len() as i32 / 2,
-        );
-        next_block.pos = (
-            next_width / 2 - next_block.pattern.len() as i32 / 2,
-            next_height / 2 - next_block.pattern[0].len() as i32 / 2,
-        );
+        // stats are shown only when there's room left for them
+        let stats_x = info_rect[0].x + info_rect[0].width;
+        if screen_rect.width >= stats_x + 26 {
+            info_rect.push(Rect {
+                x: stats_x,
+                width: (screen_rect.width - stats_x).min(32),
+                ..info_rect[0]
+            });
+            let pieces_x = stats_x + info_rect[1].width;
+            if screen_rect.width >= pieces_x + 20 {
+                info_rect.push(Rect {
+                    x: pieces_x,
+                    width: 20,
+                    ..info_rect[0]
+                });
+            }
+        }
 
         Self {
-            cursor_state: false,
-            locked: false,
-            game_state: GameState::Playing,
-            rounds: 0,
-            points: 0,
-            exit: false,
-            reset: false,
-            screen_rect,
-            next_rect,
-            info_rect,
-            board_rect,
-            filled_area,
-            game_width,
-            game_height,
-            next_width,
-            next_height,
-            current_block,
-            next_block,
-            terminal: Arc::new(Mutex::new(terminal)),
-            move_interval: Duration::from_secs_f64(0.1),
+            compact,
+            screen: screen_rect,
+            board: board_rect,
+            field: field_rect,
+            next: next_rect,
+            hold: hold_rect,
+            info: info_rect,
         }
     }
 
-    fn reset(&mut self) {
-        if self.game_state != GameState::Finished {
-            return;
+    /// Next panel sized for all upcoming pieces, each getting a 5 columns wide slot in a strip
+    /// or a 3 rows high one in a column. It takes only the room that's left, showing fewer of them
+    fn next_rect(screen_rect: Rect, compact: bool, config: &Config) -> Rect {
+        let preview_count = config.preview_count.max(1) as u16;
+        let footer_height = Layout::footer_height(compact);
+        match config.next_panel {
+            NextPanel::Strip if compact => Rect::default(),
+            NextPanel::Strip => {
+                // a single piece gets a slot as wide as hold
+                let room = screen_rect.width - FOOTER_WIDTH - 2;
+                Rect {
+                    x: screen_rect.x,
+                    y: screen_rect.y + screen_rect.height - footer_height,
+                    width: (5 * preview_count).max(9).min(room) + 2,
+                    height: footer_height,
+                }
+            }
+            NextPanel::Column => {
+                let board_height = screen_rect.height - footer_height;
+                Rect {
+                    x: screen_rect.x + screen_rect.width - NEXT_COLUMN_WIDTH,
+                    y: screen_rect.y,
+                    width: NEXT_COLUMN_WIDTH,
+                    height: (3 * preview_count + 2).min(board_height),
+                }
+            }
+        }
+    }
+
+    fn footer_height(compact: bool) -> u16 {
+        match compact {
+            true => 1,
+            false => FOOTER_HEIGHT,
         }
+    }
 
-        self.filled_area = vec![vec![Color::Black; self.game_height]; self.game_width];
+    /// Board cells fitting in the board panel,
+    /// game logic always has gravity along x, a vertical board only draws it turned
+    fn game_size(&self, orientation: Orientation) -> (usize, usize) {
+        let (width, height) = (self.board.width - 2, self.board.height - 2);
+        match orientation {
+            Orientation::Horizontal => (width as usize, height as usize * 2),
+            Orientation::Vertical => (height as usize * 2, width as usize),
+        }
+    }
+}
 
-        self.current_block = TetrisBlock::new_random();
-        self.next_block = TetrisBlock::new_random();
-        self.current_block.pos = (
-            0,
-            self.game_height as i32 / 2 - self.current_block.pattern[0].len() as i32 / 2,
-        );
-        self.next_block.pos = (
-            self.next_width / 2 - self.next_block.pattern.len() as i32 / 2,
-            self.next_height / 2 - self.next_block.pattern[0].len() as i32 / 2,
-        );
+/// How the last game stood when the player quit, printed with `--summary` after the terminal is restored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameSummary {
+    pub mode: GameMode,
+    pub finished: bool,
+    pub won: bool,
+    pub score: u64,
+    pub level: u64,
+    pub lines: u64,
+    pub time: Duration,
+}
 
-        self.rounds = 0;
-        self.points = 0;
-        self.game_state = GameState::Playing;
-        self.reset = true;
+impl fmt::Display for GameSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = match (self.finished, self.won) {
+            (true, true) => "won",
+            (true, false) => "game over",
+            (false, _) => "quit",
+        };
+        writeln!(f, "Mode:  {} ({outcome})", self.mode)?;
+        writeln!(f, "Score: {}", self.score)?;
+        writeln!(f, "Level: {}", self.level)?;
+        writeln!(f, "Lines: {}", self.lines)?;
+        write!(f, "Time:  {}", format_time(self.time))
     }
+}
 
-    pub fn run(self) -> io::Result<()> {
-        let atomic_terminal = Arc::clone(&self.terminal);
-        let atomic_self = Arc::new(Mutex::new(self));
+/// Block drawn on a canvas in given color
+struct BlockShape {
+    block: TetrisBlock,
+    color: Color,
+}
 
-        while !{ atomic_self.lock().unwrap().exit } {
-            {
-                atomic_self.lock().unwrap().reset = false;
+impl Shape for BlockShape {
+    fn draw(&self, painter: &mut Painter) {
+        for (i, col) in self.block.pattern.iter().enumerate() {
+            for (j, draw) in col.iter().enumerate() {
+                if *draw {
+                    let x = i as f64 + self.block.pos.0 as f64;
+                    let y = j as f64 + self.block.pos.1 as f64;
+                    if let Some((x, y)) = painter.get_point(x, y) {
+                        painter.paint(x, y, self.color);
+                    }
+                }
             }
+        }
+    }
+}
 
-            let join_handle = thread::spawn({
-                let atomic_terminal = atomic_terminal.clone();
-                let atomic_self = atomic_self.clone();
+impl Tetris {
+    /// Game with random pieces, or the daily challenge ones if enabled in config
+    pub fn new(screen_rect: Rect, config: Config, keys: KeyBindings) -> Self {
+        let seed = match config.daily {
+            true => Daily::today().seed,
+            false => rand::random(),
+        };
+        let mut tetris = Self::new_with_seed(screen_rect, config, keys, seed);
+        tetris.fixed_seed = tetris.daily.is_some();
+        tetris
+    }
 
-                move || loop {
-                    let (parts, part_interval) = {
-                        let atomic_self = atomic_self.lock().unwrap();
-                        let parts = (atomic_self.move_interval.as_secs_f64() / 0.1).ceil() as u32;
-                        (parts, atomic_self.move_interval / parts)
-                    };
+    /// Game with pieces fully determined by the seed, resetting replays the same pieces
+    pub fn new_with_seed(screen_rect: Rect, config: Config, keys: KeyBindings, seed: u64) -> Self {
+        let layout = Layout::new(screen_rect, &config);
+        let (game_width, game_height) = layout.game_size(config.orientation);
 
-                    for _ in 0..parts {
-                        thread::sleep(part_interval);
-                        let atomic_self = atomic_self.lock().unwrap();
-                        if atomic_self.exit || atomic_self.reset {
-                            return;
-                        };
-                    }
+        let daily = config.daily.then(Daily::today);
+        let theme = Theme::from_config(&config);
+        let mut engine = GameEngine::new(config.rules(), game_width, game_height, seed);
+        engine.game_state = Self::first_state(&config, config.attract);
 
-                    let mut atomic_self = atomic_self.lock().unwrap();
-                    if atomic_self.game_state == GameState::Playing {
-                        atomic_self.move_forward();
-                        let _ = atomic_terminal
-                            .lock()
-                            .unwrap()
-                            .draw(|frame| atomic_self.draw(frame));
-                    }
-                }
-            });
+        Self {
+            cursor_state: false,
+            dirty: true,
+            locked: false,
+            show_help: !config.shown_help_before,
+            show_ghost: true,
+            gravity_enabled: config.mode != GameMode::Zen || config.zen_gravity,
+            show_grid: false,
+            show_outline: config.current_outline,
+            pause_selection: 0,
+            pending_quit: false,
+            countdown_elapsed: Duration::ZERO,
+            sound_enabled: config.bell,
+            big: config.big,
+            scale: 1,
+            grow_with_terminal: false,
+            key_releases: false,
+            held_move: None,
+            buffered: VecDeque::new(),
+            soft_dropping: false,
+            lock_times: HashMap::new(),
+            engine,
+            start_time: Instant::now(),
+            paused_time: Duration::ZERO,
+            finished_at: None,
+            gravity_elapsed: Duration::ZERO,
+            last_frame: None,
+            exit: false,
+            screen_rect: layout.screen,
+            next_rect: layout.next,
+            hold_rect: layout.hold,
+            compact: layout.compact,
+            info_rect: layout.info,
+            board_rect: layout.board,
+            field_rect: layout.field,
+            status: None,
+            input_echo: None,
+            #[cfg(feature = "debug-overlay")]
+            debug: Default::default(),
+            finesse_fault_at: None,
+            danger_warned: false,
+            last_key: None,
+            last_tap: None,
+            attract: config.attract,
+            last_input: Instant::now(),
+            bot_plan: None,
+            color_mode: config.color_mode.resolve(),
+            theme,
+            config,
+            keys,
+            daily,
+            fixed_seed: true,
+            setup: None,
+            recorder: None,
+            follower: None,
+            replay_time: Duration::ZERO,
+        }
+    }
 
-            while !{ atomic_self.lock().unwrap().exit || atomic_self.lock().unwrap().reset } {
-                {
-                    let mut atomic_self = atomic_self.lock().unwrap();
-                    atomic_terminal
-                        .lock()
-                        .unwrap()
-                        .draw(|frame| atomic_self.draw(frame))?;
-                }
-                match event::read()? {
-                    Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
-                        atomic_self.lock().unwrap().handle_key_event(key_event)?
-                    }
-                    _ => {}
-                };
-            }
+    /// Starts this and every following game with the stack on the floor, lines have to span
+    /// the board and leave room for pieces to spawn
+    pub fn set_board(&mut self, lines: Vec<Vec<Option<u8>>>) -> Result<(), String> {
+        let across = lines.first().map_or(0, |line| line.len());
+        if across != self.engine.game_height {
+            return Err(format!(
+                "Board file lines are {across} cells across, the board is {}",
+                self.engine.game_height
+            ));
+        }
+        // pieces spawn within the first 4 lines
+        let room = self.engine.game_width.saturating_sub(4);
+        if lines.len() > room {
+            return Err(format!(
+                "Board file has {} lines, at most {room} fit below the spawn area",
+                lines.len()
+            ));
+        }
+        self.setup = Some(lines);
+        self.restart();
+        Ok(())
+    }
 
-            join_handle.join().unwrap();
+    /// Deals the custom piece set instead of tetrominoes in this and every following game,
+    /// every piece has to fit across the board whichever way it's turned
+    pub fn set_pieces(&mut self, pieces: Vec<PieceDef>) -> Result<(), String> {
+        let across = self.engine.game_height;
+        if let Some(piece) = pieces
+            .iter()
+            .find(|piece| piece.pattern.len().max(piece.pattern[0].len()) > across)
+        {
+            return Err(format!(
+                "piece {} doesn't fit on a board {across} cells across",
+                piece.name
+            ));
         }
+        self.engine.rules.pieces = pieces;
+        self.restart();
+        Ok(())
+    }
 
+    /// Logs the seed and every following input to the recorder, so the game can be replayed
+    pub fn record(&mut self, mut recorder: Recorder) -> io::Result<()> {
+        let board = (self.engine.game_width, self.engine.game_height);
+        recorder.start(self.engine.seed, board, &self.config)?;
+        if let Some(setup) = &self.setup {
+            recorder.stack(setup)?;
+        }
+        recorder.pieces(&self.engine.rules.pieces)?;
+        recorder.flush()?;
+        self.recorder = Some(recorder);
         Ok(())
     }
 
-    fn draw(&mut self, frame: &mut Frame) {
-        frame.render_widget(self, frame.area());
+    /// Plays the game from a replay as it's being written, keys only stop watching it
+    pub fn watch(&mut self, follower: Follower) {
+        self.follower = Some(follower);
+        self.replay_time = Duration::ZERO;
+        self.engine.game_state = GameState::Playing;
+        self.status = Some("Watching, <Q> stops".to_string());
+        self.dirty = true;
     }
 
-    fn handle_key_event(&mut self, key_event: KeyEvent) -> io::Result<()> {
-        match key_event.code {
-            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.exit();
-            }
-            _ => {
-                if self.locked {
-                    return Ok(());
-                }
-                match key_event.code {
-                    KeyCode::Left | KeyCode::Char('a') => self.rotate90(),
-                    KeyCode::Right | KeyCode::Char('d') => self.move_forward(),
-                    KeyCode::Up | KeyCode::Char('w') => self.move_side(MoveDirection::Up),
-                    KeyCode::Down | KeyCode::Char('s') => self.move_side(MoveDirection::Down),
-                    KeyCode::Char(' ') => self.move_till_end(),
-                    KeyCode::Char('p') => self.pause(),
-                    KeyCode::Char('r') => self.reset(),
-                    _ => {}
-                }
-            }
+    /// Adds event to the replay, timed from the start of the game
+    fn record_event(&mut self, event: &str) {
+        if let Some(recorder) = &mut self.recorder {
+            let _ = recorder.event(self.start_time.elapsed(), event);
         }
-        Ok(())
     }
 
-    fn pause(&mut self) {
-        match self.game_state {
-            GameState::Playing => self.game_state = GameState::Paused,
-            GameState::Paused => self.game_state = GameState::Playing,
-            _ => {}
+    /// Smallest screen fitting the board and the panels below it, with a single upcoming piece,
+    /// or the compact HUD on short screens
+    pub fn min_size(config: &Config) -> (u16, u16) {
+        let (width, height) = match config.next_panel {
+            NextPanel::Strip => (FOOTER_WIDTH + 11, 6),
+            NextPanel::Column => (FOOTER_WIDTH, 6),
         };
+        match config.playfield.size(config.orientation) {
+            Some((cells_width, cells_height)) => (
+                width.max(cells_width + 2 + Tetris::side_width(config)),
+                height.max(cells_height / 2 + 3),
+            ),
+            None => (width, height),
+        }
     }
 
-    fn exit(&mut self) {
-        self.exit = true;
+    /// Screen height for a board `rows` high inside its border,
+    /// short ones collapse the panels under the board into a single line
+    pub fn screen_height(config: &Config, rows: u16) -> u16 {
+        let height = rows + 2 + FOOTER_HEIGHT;
+        if config.compact_hud || height < COMPACT_BELOW {
+            rows + 3
+        } else {
+            height
+        }
     }
 
-    fn finish_round(&mut self) {
-        let (x, y) = self.current_block.pos;
-        let x = x as usize;
-        let y = y as usize;
+    /// Columns taken by panels beside the board
+    pub fn side_width(config: &Config) -> u16 {
+        match config.next_panel {
+            NextPanel::Strip => 0,
+            NextPanel::Column => NEXT_COLUMN_WIDTH,
+        }
+    }
 
-        let mut cleared_cols = 0;
-        for (i, col) in self.current_block.pattern.iter().enumerate() {
-            for (j, draw) in col.iter().enumerate() {
-                if *draw {
-                    self.filled_area[x + i][y + j] = self.current_block.color;
-                }
-            }
-            if self.filled_area[x + i].iter().all(|c| *c != Color::Black) {
-                self.filled_area[x + i]
-                    .iter_mut()
-                    .for_each(|x| *x = Color::Black);
-                self.filled_area[..x + i + 1].rotate_right(1);
-                cleared_cols += 1;
-            }
+    fn reset(&mut self) {
+        if self.engine.game_state != GameState::Finished {
+            return;
         }
 
-        self.points += match cleared_cols {
-            1 => 40,
-            2 => 100,
-            3 => 300,
-            4 => 1200,
-            _ => 0,
+        self.restart();
+    }
+
+    /// Starts a new game in place
+    fn restart(&mut self) {
+        // seeded games and daily challenge replay the same pieces
+        let seed = match self.fixed_seed {
+            true => self.engine.seed,
+            false => rand::random(),
         };
+        self.start_over(seed);
+    }
+
+    /// New game with pieces from the seed
+    fn start_over(&mut self, seed: u64) {
+        self.engine.restart(seed);
+        if let Some(setup) = &self.setup {
+            let first = self.engine.game_width - setup.len();
+            self.engine.filled_area[first..].clone_from_slice(setup);
+        }
+        self.record_event(&format!("restart {seed}"));
+
+        self.start_time = Instant::now();
+        self.paused_time = Duration::ZERO;
+        self.finished_at = None;
+        self.gravity_elapsed = Duration::ZERO;
+        self.soft_dropping = false;
+        self.buffered.clear();
+        self.lock_times.clear();
+        self.finesse_fault_at = None;
+        self.danger_warned = false;
+        self.bot_plan = None;
+        self.engine.game_state = Self::first_state(&self.config, self.attract);
+        self.countdown_elapsed = Duration::ZERO;
+        self.dirty = true;
+    }
 
-        self.rounds += 1;
+    /// Games start with a countdown, unless it's disabled or the bot plays
+    fn first_state(config: &Config, attract: bool) -> GameState {
+        if config.countdown && !attract {
+            GameState::Starting
+        } else {
+            GameState::Playing
+        }
+    }
 
-        let starting_y_pos = (self.current_block.pos.1 as usize).min(
-            self.game_height
-                - self
-                    .next_block
-                    .pattern
-                    .iter()
-                    .map(|x| x.len())
-                    .max()
-                    .unwrap_or(0),
-        );
+    /// Counts down before the game, the clock starts once it's over
+    fn tick_countdown(&mut self, elapsed: Duration) {
+        if self.engine.game_state != GameState::Starting || self.locked {
+            return;
+        }
+        let step = self.countdown_elapsed.as_secs();
+        self.countdown_elapsed += elapsed;
+        if self.countdown_elapsed.as_secs() >= COUNTDOWN.len() as u64 {
+            self.engine.game_state = GameState::Playing;
+            self.start_time = Instant::now();
+            self.paused_time = Duration::ZERO;
+        } else if self.countdown_elapsed.as_secs() == step {
+            return;
+        }
+        self.dirty = true;
+    }
 
-        for (i, col) in self.next_block.pattern.iter().enumerate() {
-            for (j, draw) in col.iter().enumerate() {
-                if *draw && self.filled_area[i][j + starting_y_pos] != Color::Black {
-                    // game lost
-                    self.game_state = GameState::Finished;
-                    return;
-                }
+    /// Plays until the player quits, returning where the game ended up. Input is polled with
+    /// a timeout so timers keep running and the screen keeps updating without any keys pressed
+    pub fn run(mut self, terminal: &mut DefaultTerminal) -> io::Result<GameSummary> {
+        // auto repeat needs to know when keys are released, which not every terminal reports
+        self.key_releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if self.key_releases {
+            execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+
+        let mut last_update = Instant::now();
+        while !self.exit {
+            if self.needs_redraw() && self.next_frame_in().is_zero() {
+                terminal.draw(|frame| self.draw(frame))?;
+                self.last_frame = Some(Instant::now());
+            }
+
+            if event::poll(self.next_update_in())? {
+                match event::read()? {
+                    Event::Key(key_event) => self.handle_key(key_event)?,
+                    Event::Mouse(mouse_event) => self.handle_mouse_event(mouse_event),
+                    Event::Resize(width, height) => self.resize(width, height),
+                    _ => {}
+                };
             }
+
+            let now = Instant::now();
+            self.update(now - last_update);
+            last_update = now;
         }
 
-        self.current_block = std::mem::replace(&mut self.next_block, TetrisBlock::new_random());
-        self.current_block.pos = (0, starting_y_pos as i32);
-        self.next_block.pos = (
-            self.next_width / 2 - self.next_block.pattern.len() as i32 / 2,
-            self.next_height / 2 - self.next_block.pattern[0].len() as i32 / 2,
-        );
+        if self.key_releases {
+            execute!(io::stdout(), PopKeyboardEnhancementFlags)?;
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.finish(self.start_time.elapsed())?;
+            recorder.flush()?;
+        }
+
+        Ok(self.summary())
     }
 
-    fn rotate90(&mut self) {
-        if self.game_state == GameState::Finished {
-            return;
+    fn summary(&self) -> GameSummary {
+        GameSummary {
+            mode: self.engine.rules.mode,
+            finished: self.engine.game_state == GameState::Finished,
+            won: self.engine.won,
+            score: self.engine.points,
+            level: self.engine.level,
+            lines: self.engine.lines,
+            time: self.play_time(),
+        }
+    }
+
+    /// Longest input can be waited for before something time based is due
+    fn next_update_in(&self) -> Duration {
+        let mut timeout = FRAME;
+        if let Some(repeat_in) = self.next_repeat_in() {
+            timeout = timeout.min(repeat_in);
         }
-        if self.game_state == GameState::Paused {
-            self.game_state = GameState::Playing
+        // changes held back by the refresh rate cap are drawn as soon as it allows
+        if self.dirty || self.engine.changed {
+            timeout = timeout.min(self.next_frame_in());
         }
+        if self.engine.game_state == GameState::Playing {
+            let gravity_in = self.gravity_interval().saturating_sub(self.gravity_elapsed);
+            timeout = timeout.min(gravity_in);
+        }
+        timeout
+    }
 
-        let new_pattern = TetrisBlock::rotate90(&self.current_block.pattern);
+    /// Time until the refresh rate cap allows drawing another frame
+    fn next_frame_in(&self) -> Duration {
+        match (self.last_frame, self.config.max_fps) {
+            (Some(last_frame), fps) if fps > 0 => {
+                (Duration::from_secs(1) / fps).saturating_sub(last_frame.elapsed())
+            }
+            _ => Duration::ZERO,
+        }
+    }
 
-        let (x, y) = self.current_block.pos;
-        let x = x as usize;
-        let y = y as usize;
+    /// Advances timers by the time since the last update: countdown, time limit, line clear
+    /// and lock delay, then applies buffered moves and gravity. Time spent paused doesn't
+    /// count, so resuming keeps the remaining gravity interval. Gravity catches up on at most
+    /// 250 ms (`MAX_CATCH_UP`) at once, so a long stall doesn't drop the piece several lines
+    fn update(&mut self, elapsed: Duration) {
+        if self.follower.is_some() {
+            // the replay's own events keep the time
+            self.follow_replay();
+            return;
+        }
+        if self.engine.game_state == GameState::Paused || self.pending_quit {
+            self.paused_time += elapsed;
+        }
+        if self.pending_quit {
+            return;
+        }
+        self.tick_countdown(elapsed);
+        if self.engine.game_state == GameState::Playing && self.time_up() {
+            self.record_event("time up");
+            self.engine.end_game(true);
+            self.track(None);
+        }
+        if self.engine.game_state == GameState::Playing {
+            self.tick_clear(elapsed);
+            self.tick_entry(elapsed);
+            self.tick_lock(elapsed);
+            self.gravity_elapsed += elapsed;
+        }
+        if self.engine.game_state == GameState::Playing && !self.engine.is_between_pieces() {
+            while let Some(action) = self.buffered.pop_front() {
+                self.record_event(action.name());
+                self.count_input(action);
+                self.apply_action(action);
+            }
+        }
+        self.auto_repeat();
 
-        for move_y in 0..4.min(y).max(1) {
-            let y = y - move_y;
-            let mut can_rotate = true;
+        self.start_idle_demo();
 
-            for (i, col) in new_pattern.iter().enumerate() {
-                for (j, draw) in col.iter().enumerate() {
-                    if *draw
-                        && (x + i >= self.game_width
-                            || y + j >= self.game_height
-                            || self.filled_area[x + i][y + j] != Color::Black)
-                    {
-                        can_rotate = false;
-                        break;
-                    }
-                }
-                if !can_rotate {
-                    break;
-                }
+        self.gravity_elapsed = self.gravity_elapsed.min(MAX_CATCH_UP);
+        // the time past a step carries over, a late update makes up the missed steps
+        // instead of pushing every later step back
+        while self.gravity_elapsed >= self.gravity_interval() {
+            if self.engine.is_between_pieces() {
+                self.gravity_elapsed = Duration::ZERO;
+                return;
             }
-
-            if can_rotate {
-                self.current_block.pattern = new_pattern;
-                self.current_block.pos.1 -= move_y as i32;
+            self.gravity_elapsed -= self.gravity_interval();
+            if self.attract {
+                self.bot_step();
+            }
+            if self.engine.game_state != GameState::Playing {
                 return;
             }
+            if self.soft_dropping {
+                self.record_event(Action::SoftDrop.name());
+                self.soft_drop();
+            } else if self.gravity_enabled {
+                self.record_event("gravity");
+                self.move_forward();
+            }
         }
     }
 
-    fn move_forward(&mut self) {
-        if self.game_state == GameState::Finished {
+    /// Plays the events written to the watched replay since the last update
+    fn follow_replay(&mut self) {
+        let Some(follower) = self.follower.as_mut().filter(|follower| !follower.ended()) else {
             return;
+        };
+        let events = match follower.events() {
+            Ok(events) => events,
+            Err(error) => {
+                self.status = Some(format!("Can't read replay: {error}"));
+                self.dirty = true;
+                return;
+            }
+        };
+        let ended = follower.ended();
+        for (time, event) in events {
+            self.replay_event(time, &event);
         }
-        if self.game_state == GameState::Paused {
-            self.game_state = GameState::Playing
+        if ended {
+            self.status = Some("Replay ended, <Q> quits".to_string());
+            self.dirty = true;
         }
+    }
 
-        let (x, y) = self.current_block.pos;
-        let x = x as usize;
-        let y = y as usize;
-        for (i, col) in self.current_block.pattern.iter().enumerate() {
-            for (j, draw) in col.iter().enumerate() {
-                if *draw
-                    && (x + i + 1 >= self.filled_area.len()
-                        || self.filled_area[x + i + 1][y + j] != Color::Black)
-                {
-                    // stop, next move
-                    self.finish_round();
-                    return;
-                }
-            }
-        }
-        self.current_block.pos.0 += 1;
-    }
-
-    fn get_end_move_pos(&self) -> (i32, i32) {
-        let (x, y) = self.current_block.pos;
-        let mut x: usize = x as usize;
-        let y = y as usize;
-        loop {
-            for (i, col) in self.current_block.pattern.iter().enumerate() {
-                for (j, draw) in col.iter().enumerate() {
-                    if *draw
-                        && (x + i + 1 >= self.filled_area.len()
-                            || self.filled_area[x + i + 1][y + j] != Color::Black)
-                    {
-                        // stop
-                        return (x as i32, y as i32);
-                    }
+    /// Timers run up to the time of the event, then it's applied the way it was recorded.
+    /// Events that only changed the player's screen or wrote files are skipped
+    fn replay_event(&mut self, time: Duration, event: &str) {
+        if self.engine.game_state == GameState::Playing {
+            let elapsed = time.saturating_sub(self.replay_time);
+            self.tick_clear(elapsed);
+            self.tick_entry(elapsed);
+            self.tick_lock(elapsed);
+        }
+        self.replay_time = time;
+        self.dirty = true;
+
+        let paused = self.engine.game_state == GameState::Paused;
+        let (name, value) = event.split_once(' ').unwrap_or((event, ""));
+        match (name, value) {
+            ("gravity", _) => self.move_forward(),
+            ("time", "up") => {
+                self.engine.end_game(true);
+                self.track(None);
+            }
+            ("wall", direction) => {
+                let direction = match direction {
+                    "up" => MoveDirection::Up,
+                    _ => MoveDirection::Down,
+                };
+                while self.engine.move_side(direction) {}
+            }
+            ("restart", seed) => {
+                if let Ok(seed) = seed.parse() {
+                    self.start_over(seed);
+                    self.engine.game_state = GameState::Playing;
+                    self.replay_time = Duration::ZERO;
+                }
+            }
+            ("resize", size) => {
+                let size = size
+                    .split_once(' ')
+                    .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)));
+                if let Some((width, height)) = size {
+                    self.resize_board(width, height);
                 }
             }
-            x += 1;
+            // leaving the pause menu, picking restart or quit is followed by their own events
+            ("key", "enter" | "esc") if paused => self.pause(),
+            ("key", "u") => self.undo(),
+            ("key", key) if key.chars().eq([self.config.clear_board_key]) => self.clear_board(),
+            _ => match Action::from_name(name) {
+                Some(Action::Pause) => self.pause(),
+                Some(Action::Save | Action::Quit) | None => {}
+                Some(_) if paused => {}
+                Some(action) => {
+                    self.skip_entry(Some(action));
+                    self.apply_action(action);
+                }
+            },
         }
     }
 
-    fn move_till_end(&mut self) {
-        if self.game_state == GameState::Finished {
+    /// Bot starts playing a demo once the game over screen was left alone for `idle_demo_ms`
+    fn start_idle_demo(&mut self) {
+        let idle = Duration::from_millis(self.config.idle_demo_ms);
+        if self.attract
+            || idle.is_zero()
+            || self.engine.game_state != GameState::Finished
+            || self.last_input.elapsed() < idle
+        {
             return;
         }
-        if self.game_state == GameState::Paused {
-            self.game_state = GameState::Playing
-        }
+        self.attract = true;
+        self.restart();
+    }
 
-        self.current_block.pos.0 = self.get_end_move_pos().0;
-        self.finish_round();
+    /// Any input during the demo starts a fresh game for the player
+    fn end_demo(&mut self) {
+        self.attract = false;
+        self.restart();
     }
 
-    fn move_side(&mut self, direction: MoveDirection) {
-        if self.game_state == GameState::Finished {
-            return;
-        }
-        if self.game_state == GameState::Paused {
-            self.game_state = GameState::Playing
+    /// Time between gravity steps, `soft_drop_factor` times shorter while soft dropping
+    fn gravity_interval(&self) -> Duration {
+        match self.soft_dropping {
+            true => self.engine.move_interval / self.config.soft_drop_factor.max(1),
+            false => self.engine.move_interval,
         }
+    }
 
-        let (x, y) = self.current_block.pos;
-        let x = x as usize;
-        let y = y as usize;
-        let direction = match direction {
-            MoveDirection::Down => -1,
-            MoveDirection::Up => 1,
-        };
-        for (i, col) in self.current_block.pattern.iter().enumerate() {
-            for (j, draw) in col.iter().enumerate() {
-                if *draw
-                    && ((y + j == 0 && direction < 0)
-                        || (y + j + 1 == self.game_height && direction > 0)
-                        || self.filled_area[x + i][(y as i32 + j as i32 + direction) as usize]
-                            != Color::Black)
-                {
-                    // can't move there
-                    return;
+    /// Releases stop auto repeat, repeats of a held move key are replaced by own timing
+    fn handle_key(&mut self, key_event: KeyEvent) -> io::Result<()> {
+        #[cfg(feature = "debug-overlay")]
+        {
+            self.debug.record(key_event);
+            self.dirty = true;
+            if key_event.code == KeyCode::F(12) && key_event.kind == KeyEventKind::Press {
+                self.debug.visible = !self.debug.visible;
+                return Ok(());
+            }
+        }
+        match key_event.kind {
+            KeyEventKind::Release => {
+                if self.held_move.map(|(held, _)| held) == self.move_direction(&key_event) {
+                    self.held_move = None;
+                }
+                if self.keys.action(&key_event) == Some(Action::SoftDrop) {
+                    self.soft_dropping = false;
                 }
+                Ok(())
             }
+            KeyEventKind::Repeat if self.move_direction(&key_event).is_some() => Ok(()),
+            KeyEventKind::Repeat if self.soft_dropping => Ok(()),
+            _ => self.handle_key_event(key_event),
         }
-        self.current_block.pos.1 += direction;
     }
-}
 
-impl Widget for &mut Tetris {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        let last_point_exists = buf
-            .cell((self.screen_rect.width - 1, self.screen_rect.height - 1))
-            .is_some();
+    fn move_direction(&self, key_event: &KeyEvent) -> Option<MoveDirection> {
+        match self.keys.action(key_event) {
+            Some(Action::MoveUp) => Some(MoveDirection::Up),
+            Some(Action::MoveDown) => Some(MoveDirection::Down),
+            _ => None,
+        }
+    }
 
-        if last_point_exists {
-            self.locked = false;
-            let next = Canvas::default()
-                .block(
-                    Block::bordered()
-                        .bold()
-                        .title_top(" Next ".bold().green())
-                        .title_bottom(" <Space> ".bold().blue())
-                        .title_alignment(Alignment::Center),
-                )
-                .background_color(Color::Black)
-                .marker(ratatui::symbols::Marker::HalfBlock)
-                .x_bounds([-1.0, self.next_width as f64 - 1.0])
-                .y_bounds([0.0, self.next_height as f64])
-                .paint(|ctx| {
-                    ctx.layer();
-                    ctx.draw(&self.next_block);
-                });
+    /// Holding a move key moves the piece again after `das_ms`, then every `arr_ms`
+    fn start_auto_repeat(&mut self, direction: MoveDirection) {
+        if self.key_releases {
+            let delay = Duration::from_millis(self.config.das_ms);
+            self.held_move = Some((direction, Instant::now() + delay));
+        }
+    }
 
-            next.render(self.next_rect, buf);
+    /// Second press of a move key within `double_tap_ms` keeps moving the piece
+    /// until the wall or the stack stops it
+    fn double_tap(&mut self, direction: MoveDirection) {
+        let now = Instant::now();
+        let window = Duration::from_millis(self.config.double_tap_ms);
+        let tapped = self
+            .last_tap
+            .is_some_and(|(last, time)| last == direction && now - time < window);
+        self.last_tap = Some((direction, now));
+        if !self.config.double_tap || !tapped {
+            return;
+        }
+        // a third tap starts over
+        self.last_tap = None;
+        self.record_event(match direction {
+            MoveDirection::Up => "wall up",
+            MoveDirection::Down => "wall down",
+        });
+        while self.engine.move_side(direction) {}
+    }
 
-            let shortcut_info = if self.game_state == GameState::Finished {
-                " <Ctrl + C>".bold().blue()
-                    + " Exit ".not_bold().white()
-                    + "<R>".bold().blue()
-                    + " Reset ".not_bold().white()
-            } else {
-                " <Ctrl + C>".bold().blue()
-                    + " Exit ".not_bold().white()
-                    + "<P>".bold().blue()
-                    + " Pause ".not_bold().white()
-            };
+    fn next_repeat_in(&self) -> Option<Duration> {
+        self.held_move
+            .map(|(_, next)| next.saturating_duration_since(Instant::now()))
+    }
 
-            let info = Paragraph::new(Text::from(vec![
-                text::Line::from(vec![
-                    " Score: ".white(),
-                    self.points.to_string().bold().green(),
-                ]),
-                text::Line::from(vec![
-                    " Round: ".white(),
-                    self.rounds.to_string().bold().blue(),
-                ]),
-                text::Line::from(vec![
-                    " State: ".white(),
-                    match self.game_state {
-                        GameState::Playing => "Playing".to_string().green(),
-                        GameState::Paused => "Paused".to_string().yellow().bold(),
-                        GameState::Finished => "Finished".to_string().red().bold(),
-                    },
-                ]),
-            ]))
-            .block(
-                Block::bordered()
-                    .title_top(" Info ".bold().green())
-                    .title_bottom(shortcut_info)
-                    .title_alignment(Alignment::Center),
-            );
+    fn auto_repeat(&mut self) {
+        let Some((direction, next)) = self.held_move else {
+            return;
+        };
+        if self.engine.game_state != GameState::Playing || self.engine.is_between_pieces() {
+            self.held_move = None;
+            return;
+        }
+        if Instant::now() < next {
+            return;
+        }
+        self.record_event(move_action(direction).name());
+        self.apply_action(move_action(direction));
+        let interval = Duration::from_millis(self.config.arr_ms.max(1));
+        self.held_move = Some((direction, next + interval));
+    }
 
-            info.render(self.info_rect[0], buf);
+    fn draw(&mut self, frame: &mut Frame) {
+        frame.render_widget(self, frame.area());
+    }
 
-            let board = Canvas::default()
-                .block(
-                    Block::bordered()
-                        .bold()
-                        .fg(Color::Gray)
-                        .title_top(" Tetris ".bold().green())
-                        .title_bottom(
-                            " <A/←>".bold().blue()
-                                + " Rotate ".white().not_bold()
-                                + "<W/↑, S/↓, D/→>".bold().blue()
-                                + " Move ".white().not_bold(),
-                        )
-                        .title_alignment(Alignment::Center),
-                )
-                .background_color(Color::Black)
-                .marker(ratatui::symbols::Marker::HalfBlock)
-                .x_bounds([-1.0, self.game_width as f64 - 1.0])
-                .y_bounds([0.0, self.game_height as f64])
-                .paint(|ctx| {
-                    ctx.layer();
+    /// In quiet mode frames are drawn only after something changed
+    fn needs_redraw(&self) -> bool {
+        !self.config.quiet || self.dirty || self.engine.changed
+    }
 
-                    let mut painter = Painter::from(&mut *ctx);
-                    for (x, col) in self.filled_area.iter().enumerate() {
-                        for (y, color) in col.iter().enumerate() {
-                            if *color != Color::Black {
-                                if let Some((x, y)) = painter.get_point(x as f64, y as f64) {
-                                    painter.paint(x, y, *color);
+    fn handle_key_event(&mut self, key_event: KeyEvent) -> io::Result<()> {
+        if self.follower.is_some() {
+            // the watched game plays itself
+            let ctrl_c = key_event.code == KeyCode::Char('c')
+                && key_event.modifiers.contains(KeyModifiers::CONTROL);
+            if ctrl_c || key_event.code == KeyCode::Char('q') {
+                self.exit();
+            }
+            return Ok(());
+        }
+        if self.pending_quit {
+            // only a yes quits, anything else goes back to the game
+            self.pending_quit = false;
+            self.dirty = true;
+            if key_event.code == KeyCode::Char('y') {
+                self.exit();
+            }
+            return Ok(());
+        }
+        let action = self.keys.action(&key_event);
+        // Ctrl + C always quits, even when quit is bound to other keys
+        let ctrl_c = key_event.code == KeyCode::Char('c')
+            && key_event.modifiers.contains(KeyModifiers::CONTROL);
+        match action {
+            Some(Action::Quit) => self.apply_action(Action::Quit),
+            _ if ctrl_c => self.apply_action(Action::Quit),
+            _ => {
+                self.last_input = Instant::now();
+                if self.attract {
+                    self.end_demo();
+                    return Ok(());
+                }
+                if self.locked {
+                    return Ok(());
+                }
+                self.skip_entry(action);
+                if self.is_waiting() {
+                    self.buffer(action);
+                    return Ok(());
+                }
+                if self.is_key_chatter(key_event.code) {
+                    return Ok(());
+                }
+                if self.status.take().is_some() {
+                    self.dirty = true;
+                }
+                match action {
+                    Some(action) => self.record_event(action.name()),
+                    None => {
+                        if let Some(name) = key_name(&key_event) {
+                            self.record_event(&format!("key {name}"));
+                        }
+                    }
+                }
+                if self.engine.game_state == GameState::Paused {
+                    self.handle_pause_menu_key(key_event.code, action);
+                    return Ok(());
+                }
+                if let Some(action) = action {
+                    self.count_input(action);
+                    self.apply_action(action);
+                    if let Some(direction) = self.move_direction(&key_event) {
+                        self.double_tap(direction);
+                        self.start_auto_repeat(direction);
+                    }
+                    if action == Action::SoftDrop {
+                        // without key releases the system key repeat keeps dropping
+                        self.soft_dropping = self.key_releases;
+                    }
+                    return Ok(());
+                }
+                match key_event.code {
+                    KeyCode::Char('r') => self.reset(),
+                    KeyCode::Char('u') => self.undo(),
+                    KeyCode::Char('e') => self.export(),
+                    KeyCode::Char(c) if c == self.config.dump_config_key => self.dump_config(),
+                    KeyCode::Char(c) if c == self.config.clear_board_key => self.clear_board(),
+                    KeyCode::Char('?') => {
+                        self.show_help = !self.show_help;
+                        self.dirty = true;
+                    }
+                    KeyCode::Char('g') => {
+                        self.show_ghost = !self.show_ghost;
+                        self.dirty = true;
+                    }
+                    KeyCode::Char('l') => {
+                        self.show_grid = !self.show_grid;
+                        self.dirty = true;
+                    }
+                    KeyCode::Char('c') => {
+                        self.show_outline = !self.show_outline;
+                        self.dirty = true;
+                    }
+                    KeyCode::Char('f') => {
+                        self.gravity_enabled = !self.gravity_enabled;
+                        self.gravity_elapsed = Duration::ZERO;
+                        self.status = Some(
+                            match self.gravity_enabled {
+                                true => "Gravity on",
+                                false => "Gravity off",
+                            }
+                            .to_string(),
+                        );
+                        self.dirty = true;
+                    }
+                    KeyCode::Char('z') => {
+                        self.big = !self.big;
+                        self.status = Some(
+                            match self.big {
+                                true => "Big board",
+                                false => "Normal board",
+                            }
+                            .to_string(),
+                        );
+                        self.dirty = true;
+                    }
+                    KeyCode::Char('b') => {
+                        self.sound_enabled = !self.sound_enabled;
+                        self.status = Some(
+                            match self.sound_enabled {
+                                true => "Sound on",
+                                false => "Sound off",
+                            }
+                            .to_string(),
+                        );
+                        self.dirty = true;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Countdown, cleared lines flashing or the entry delay, the game can't take moves yet
+    fn is_waiting(&self) -> bool {
+        self.engine.game_state == GameState::Starting || self.engine.is_between_pieces()
+    }
+
+    /// Gameplay input during the entry delay brings the next piece in at once, so it can act on it
+    fn skip_entry(&mut self, action: Option<Action>) {
+        let gameplay = matches!(
+            action,
+            Some(
+                Action::Rotate
+                    | Action::SoftDrop
+                    | Action::MoveUp
+                    | Action::MoveDown
+                    | Action::HardDrop
+                    | Action::Hold
+            )
+        );
+        if gameplay && self.engine.game_state == GameState::Playing && self.engine.skip_entry() {
+            self.track(None);
+        }
+    }
+
+    /// Keeps moves and rotations made while waiting, up to `input_buffer` of them,
+    /// anything else is dropped
+    fn buffer(&mut self, action: Option<Action>) {
+        if let Some(action @ (Action::Rotate | Action::MoveUp | Action::MoveDown)) = action {
+            if self.buffered.len() < self.config.input_buffer {
+                self.buffered.push_back(action);
+            }
+        }
+    }
+
+    /// Single entry point for gameplay input, keys, mouse, auto repeat and the demo bot
+    /// are all turned into actions applied here
+    pub fn apply_action(&mut self, action: Action) {
+        match action {
+            Action::Rotate => {
+                self.echo("Rotate");
+                self.rotate90();
+            }
+            Action::SoftDrop => {
+                self.echo("Forward");
+                self.soft_drop();
+            }
+            Action::MoveUp => {
+                self.echo("Up");
+                self.move_side(MoveDirection::Up);
+            }
+            Action::MoveDown => {
+                self.echo("Down");
+                self.move_side(MoveDirection::Down);
+            }
+            Action::HardDrop => {
+                self.echo("Drop");
+                self.move_till_end();
+            }
+            Action::Hold => {
+                self.echo("Hold");
+                self.hold();
+            }
+            Action::Pause => {
+                self.echo("Pause");
+                self.pause();
+            }
+            Action::Save => self.save_game(),
+            Action::Quit => self.request_quit(),
+        }
+    }
+
+    /// Scrolling over the board moves the piece, left click rotates it and right click drops it.
+    /// In the pause menu scrolling changes the selection and left click picks it
+    fn handle_mouse_event(&mut self, mouse_event: MouseEvent) {
+        if self.pending_quit
+            || self
+                .board_cell(mouse_event.column, mouse_event.row)
+                .is_none()
+        {
+            return;
+        }
+        let action = match mouse_event.kind {
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollRight => Action::MoveUp,
+            MouseEventKind::ScrollDown | MouseEventKind::ScrollLeft => Action::MoveDown,
+            MouseEventKind::Down(MouseButton::Left) => Action::Rotate,
+            MouseEventKind::Down(MouseButton::Right) => Action::HardDrop,
+            _ => return,
+        };
+        self.last_input = Instant::now();
+        if self.attract {
+            self.end_demo();
+            return;
+        }
+        if self.locked {
+            return;
+        }
+        self.skip_entry(Some(action));
+        if self.is_waiting() {
+            self.buffer(Some(action));
+            return;
+        }
+        if self.status.take().is_some() {
+            self.dirty = true;
+        }
+        self.record_event(action.name());
+        if self.engine.game_state == GameState::Paused {
+            let code = match action {
+                Action::Rotate => KeyCode::Enter,
+                _ => KeyCode::Null,
+            };
+            self.handle_pause_menu_key(code, Some(action));
+            return;
+        }
+        self.count_input(action);
+        self.apply_action(action);
+    }
+
+    /// With `confirm_quit` quitting a game in progress waits for a yes to the question,
+    /// see `handle_key_event`
+    fn request_quit(&mut self) {
+        let in_progress = self.engine.game_state != GameState::Finished && !self.attract;
+        if self.config.confirm_quit && in_progress {
+            self.pending_quit = true;
+            self.dirty = true;
+        } else {
+            self.exit();
+        }
+    }
+
+    /// Player's own rotations and moves count towards finesse, auto repeat and the bot don't
+    fn count_input(&mut self, action: Action) {
+        if matches!(action, Action::Rotate | Action::MoveUp | Action::MoveDown) {
+            self.engine.count_input();
+        }
+    }
+
+    /// Board cell under a terminal cell. A board cell is half of a terminal cell high,
+    /// in big mode two terminal cells wide, when there are two the upper one is returned
+    fn board_cell(&self, column: u16, row: u16) -> Option<(usize, usize)> {
+        let inner = Block::bordered().inner(self.board_rect);
+        if !inner.contains(Position::new(column, row)) {
+            return None;
+        }
+        let column = ((column - inner.x) / self.scale) as usize;
+        let row = ((row - inner.y) * 2 / self.scale) as usize;
+        let cell = if self.is_vertical() {
+            (row, column)
+        } else {
+            (column, self.engine.game_height.checked_sub(row + 1)?)
+        };
+        (cell.0 < self.engine.game_width && cell.1 < self.engine.game_height).then_some(cell)
+    }
+
+    /// While paused keys only navigate the menu, so stray moves don't resume the game
+    fn handle_pause_menu_key(&mut self, code: KeyCode, action: Option<Action>) {
+        let count = PAUSE_MENU.len();
+        match (code, action) {
+            (KeyCode::Up, _) | (_, Some(Action::MoveUp)) => {
+                self.pause_selection = (self.pause_selection + count - 1) % count;
+            }
+            (KeyCode::Down, _) | (_, Some(Action::MoveDown)) => {
+                self.pause_selection = (self.pause_selection + 1) % count;
+            }
+            (KeyCode::Enter, _) => match PAUSE_MENU[self.pause_selection] {
+                "Restart" => self.restart(),
+                "Quit" => self.exit(),
+                _ => self.pause(),
+            },
+            (KeyCode::Esc, _) | (_, Some(Action::Pause)) => self.pause(),
+            (KeyCode::Left, _) => {
+                if !self.engine.undo() {
+                    self.status = Some("Start of the history".to_string());
+                }
+            }
+            (KeyCode::Right, _) => {
+                if !self.engine.redo() {
+                    self.status = Some("End of the history".to_string());
+                }
+            }
+            (KeyCode::Char('?'), _) => self.show_help = !self.show_help,
+            _ => return,
+        }
+        self.dirty = true;
+    }
+
+    /// In debounced mode the same key repeated within the window is treated as accidental
+    fn is_key_chatter(&mut self, code: KeyCode) -> bool {
+        let now = Instant::now();
+        let window = Duration::from_millis(self.config.debounce_ms);
+        let chatter = self.config.input_mode == InputMode::Debounced
+            && self
+                .last_key
+                .is_some_and(|(last, time)| last == code && now - time < window);
+        self.last_key = Some((code, now));
+        chatter
+    }
+
+    /// Remembers action for the input echo overlay
+    fn echo(&mut self, action: &'static str) {
+        if self.config.input_echo {
+            self.input_echo = Some((action, Instant::now()));
+            self.dirty = true;
+        }
+    }
+
+    fn pause(&mut self) {
+        match self.engine.game_state {
+            GameState::Playing => {
+                self.engine.game_state = GameState::Paused;
+                self.pause_selection = 0;
+            }
+            GameState::Paused => {
+                // playing on from an earlier point of the timeline drops the later locks
+                self.engine.clear_redo();
+                self.engine.game_state = GameState::Playing;
+            }
+            _ => return,
+        };
+        self.dirty = true;
+    }
+
+    fn dump_config(&mut self) {
+        let path = "tetris-config.toml";
+        self.status = Some(match self.config.save_to(path.as_ref()) {
+            Ok(()) => format!("Saved {path}"),
+            Err(error) => format!("Save failed: {error}"),
+        });
+        self.dirty = true;
+    }
+
+    /// Writes the game to `tetris-save.json` in current directory, resumed with `--load`
+    fn save_game(&mut self) {
+        let path = "tetris-save.json";
+        self.status = Some(match self.snapshot().save_to(path.as_ref()) {
+            Ok(()) => format!("Saved {path}"),
+            Err(error) => format!("Save failed: {error}"),
+        });
+        self.dirty = true;
+    }
+
+    /// Writes the board to `tetris-board-<unix time>.ans` with colors and to a `.txt` without them
+    fn export(&mut self) {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let path = format!("tetris-board-{secs}");
+        let written = fs::write(format!("{path}.ans"), self.export_board(true))
+            .and_then(|()| fs::write(format!("{path}.txt"), self.export_board(false)));
+        self.status = Some(match written {
+            Ok(()) => format!("Exported {path}.ans"),
+            Err(error) => format!("Export failed: {error}"),
+        });
+        self.dirty = true;
+    }
+
+    /// Board with the falling piece as text, turned like on the screen with every cell two
+    /// characters wide. With `colors` cells are ANSI 256 color blocks, otherwise `[]`
+    pub fn export_board(&self, colors: bool) -> String {
+        let engine = &self.engine;
+        let (rows, cols) = match self.is_vertical() {
+            true => (engine.game_width, engine.game_height),
+            false => (engine.game_height, engine.game_width),
+        };
+        let mut grid = vec![vec![EMPTY; cols]; rows];
+        let mut put = |x: usize, y: usize, cell: Option<u8>| {
+            let (row, col) = match self.is_vertical() {
+                true => (x, y),
+                false => (engine.game_height - 1 - y, x),
+            };
+            grid[row][col] = cell;
+        };
+        for (x, line) in engine.filled_area.iter().enumerate() {
+            for (y, cell) in line.iter().enumerate() {
+                put(x, y, *cell);
+            }
+        }
+        if !engine.is_between_pieces() {
+            let block = &engine.current_block;
+            for (i, col) in block.pattern.iter().enumerate() {
+                for (j, filled) in col.iter().enumerate() {
+                    let x = block.pos.0 as usize + i;
+                    if let Some(y) = engine.cell_y(block.pos.1 + j as i32).filter(|_| *filled) {
+                        put(x, y, Some(block.color));
+                    }
+                }
+            }
+        }
+
+        let edge = format!("+{}+\n", "-".repeat(cols * 2));
+        let mut text = edge.clone();
+        for row in grid {
+            text.push('|');
+            for cell in row {
+                match (cell, colors) {
+                    (EMPTY, _) => text.push_str("  "),
+                    (Some(color), true) => text.push_str(&format!("\x1b[38;5;{color}m██\x1b[0m")),
+                    (Some(_), false) => text.push_str("[]"),
+                }
+            }
+            text.push_str("|\n");
+        }
+        text.push_str(&edge);
+        text
+    }
+
+    /// Board grows with the terminal, instead of keeping the size it started with
+    pub fn grow_with_terminal(&mut self) {
+        self.grow_with_terminal = true;
+    }
+
+    /// Fits a bigger board into a grown terminal. Shrinking keeps the board,
+    /// the game is paused while the terminal is too small for it.
+    /// A standard board keeps its size, staying in the middle of the terminal
+    fn resize(&mut self, width: u16, height: u16) {
+        self.dirty = true;
+        if !self.grow_with_terminal {
+            return;
+        }
+        let layout = Layout::new(Rect::new(0, 0, width, height), &self.config);
+        if self.config.playfield == Playfield::Fit {
+            let (game_width, game_height) = layout.game_size(self.config.orientation);
+            if !self.engine.grow(game_width, game_height) {
+                return;
+            }
+            let (width, height) = self.board_size();
+            self.record_event(&format!("resize {width} {height}"));
+        }
+        self.set_layout(layout);
+    }
+
+    /// Grows the board to the size `--width` and `--height` would give it,
+    /// the screen fitting it like at the start of such a game
+    fn resize_board(&mut self, width: u16, height: u16) {
+        let screen = Rect::new(
+            0,
+            0,
+            width.saturating_add(2 + Tetris::side_width(&self.config)),
+            Tetris::screen_height(&self.config, height / 2),
+        );
+        let layout = Layout::new(screen, &self.config);
+        let (game_width, game_height) = layout.game_size(self.config.orientation);
+        if self.engine.grow(game_width, game_height) {
+            self.set_layout(layout);
+        }
+    }
+
+    fn set_layout(&mut self, layout: Layout) {
+        self.screen_rect = layout.screen;
+        self.board_rect = layout.board;
+        self.field_rect = layout.field;
+        self.next_rect = layout.next;
+        self.hold_rect = layout.hold;
+        self.compact = layout.compact;
+        self.info_rect = layout.info;
+        self.scale = 1;
+        self.dirty = true;
+    }
+
+    /// Board size in cells as `--width` and `--height` take it
+    fn board_size(&self) -> (usize, usize) {
+        let (width, height) = (self.engine.game_width, self.engine.game_height);
+        match self.config.orientation {
+            Orientation::Horizontal => (width, height),
+            Orientation::Vertical => (height, width),
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let (width, height) = self.board_size();
+        Snapshot {
+            width: width as u16,
+            height: height as u16,
+            ..self.engine.snapshot()
+        }
+    }
+
+    /// Continues a saved game, its board has to be the size of this one.
+    /// A game saved while playing starts with the countdown again, if it's enabled
+    pub fn restore(&mut self, snapshot: Snapshot) -> Result<(), String> {
+        let (width, height) = self.board_size();
+        if (snapshot.width as usize, snapshot.height as usize) != (width, height)
+            || snapshot.filled_area.len() != self.engine.game_width
+            || snapshot
+                .filled_area
+                .iter()
+                .any(|x| x.len() != self.engine.game_height)
+        {
+            return Err(format!(
+                "Saved board is {}x{}, this one is {width}x{height}",
+                snapshot.width, snapshot.height
+            ));
+        }
+        self.restart();
+        // pieces after the saved ones come from the saved seed
+        self.engine.restart(snapshot.seed);
+        let game_state = snapshot.game_state.clone();
+        self.engine.load_snapshot(snapshot);
+        self.engine.game_state = match game_state {
+            GameState::Playing | GameState::Starting => Self::first_state(&self.config, false),
+            state => state,
+        };
+        if self.engine.game_state == GameState::Finished {
+            self.finished_at = Some(Instant::now());
+        }
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Takes back the last locked piece, it returns to the spawn edge on the same side position
+    fn undo(&mut self) {
+        if self.engine.game_state != GameState::Finished && !self.engine.undo() {
+            self.status = Some("Nothing to undo".to_string());
+            self.dirty = true;
+        }
+    }
+
+    /// Empties the board in practice mode, the daily challenge can't be practiced on
+    fn clear_board(&mut self) {
+        if !self.config.practice || self.daily.is_some() || !self.engine.clear_board() {
+            return;
+        }
+        self.danger_warned = false;
+        self.status = Some("Board cleared".to_string());
+        self.dirty = true;
+    }
+
+    /// Follows up on a lock: records its time for fading, rings the bell for cleared lines,
+    /// danger and the game ending, and marks finesse faults
+    fn track(&mut self, result: Option<LockResult>) {
+        if result.is_some() {
+            self.soft_dropping = false;
+        }
+        if result.is_some() && self.config.fading {
+            let engine = &self.engine;
+            let on_board: HashSet<u64> = engine
+                .filled_area
+                .iter()
+                .zip(&engine.placed)
+                .flat_map(|(col, placed)| col.iter().zip(placed))
+                .filter(|(cell, _)| **cell != EMPTY)
+                .map(|(_, round)| *round)
+                .collect();
+            self.lock_times.retain(|round, _| on_board.contains(round));
+            // cells from before the first recorded lock, like loaded ones, count as long faded
+            for round in on_board {
+                self.lock_times.entry(round).or_insert(Duration::ZERO);
+            }
+            // undone locks are played again under the same round
+            let round = self.engine.rounds.saturating_sub(1);
+            let play_time = self.play_time();
+            self.lock_times.insert(round, play_time);
+        }
+        if result.is_some_and(|result| result.cleared > 0) {
+            self.bell(1);
+        }
+        if result.is_some_and(|result| result.finesse_fault) {
+            self.finesse_fault_at = Some(Instant::now());
+        }
+        if result.is_some() {
+            let danger = self.in_danger();
+            if danger && !self.danger_warned {
+                self.bell(2);
+            }
+            self.danger_warned = danger;
+        }
+        if self.engine.game_state == GameState::Finished && self.finished_at.is_none() {
+            self.finished_at = Some(Instant::now());
+            self.bell(3);
+        }
+    }
+
+    /// Stack under the area where the next piece appears is within `danger_distance` cells
+    /// of the spawn edge
+    fn in_danger(&self) -> bool {
+        let engine = &self.engine;
+        if self.config.danger_distance == 0 || engine.game_state != GameState::Playing {
+            return false;
+        }
+        let next = engine.next_block();
+        let start_y = engine.next_spawn_y();
+        let end_y = start_y + next.pattern.iter().map(|x| x.len()).max().unwrap_or(0);
+        let profile = engine.height_profile();
+        let highest = profile
+            .get(start_y..end_y.min(profile.len()))
+            .and_then(|spawn| spawn.iter().max().copied())
+            .unwrap_or(0);
+        highest + self.config.danger_distance >= engine.game_width
+    }
+
+    /// Rings the terminal bell, between frames so it doesn't interrupt drawing
+    fn bell(&self, count: usize) {
+        if !self.sound_enabled {
+            return;
+        }
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all("\x07".repeat(count).as_bytes());
+        let _ = stdout.flush();
+    }
+
+    /// Color of a locked cell in fading mode, closer to the background the longer ago it was placed.
+    /// Cells without a recorded lock, like ones loaded from a save, are shown until the next lock
+    fn faded(&self, color: Color, round: u64, play_time: Duration) -> Color {
+        let Some(locked) = self.lock_times.get(&round).filter(|_| self.config.fading) else {
+            return color;
+        };
+        let fade = Duration::from_millis(self.config.fade_ms.max(1));
+        let amount = play_time.saturating_sub(*locked).as_secs_f64() / fade.as_secs_f64();
+        blend(color, self.theme.background, amount)
+    }
+
+    /// Ultra game ran out of time
+    fn time_up(&self) -> bool {
+        self.engine
+            .rules
+            .mode
+            .time_left(self.play_time())
+            .is_some_and(|left| left.is_zero())
+    }
+
+    /// Time since the game started, without time spent paused
+    fn play_time(&self) -> Duration {
+        self.finished_at
+            .unwrap_or_else(Instant::now)
+            .duration_since(self.start_time)
+            .saturating_sub(self.paused_time)
+    }
+
+    fn exit(&mut self) {
+        self.exit = true;
+    }
+
+    fn rotate90(&mut self) {
+        self.engine.rotate90();
+    }
+
+    fn move_forward(&mut self) {
+        let result = self.engine.move_forward();
+        self.track(result);
+    }
+
+    fn soft_drop(&mut self) {
+        let result = self.engine.soft_drop();
+        self.track(result);
+    }
+
+    fn move_till_end(&mut self) {
+        let result = self.engine.move_till_end();
+        self.track(result);
+    }
+
+    fn move_side(&mut self, direction: MoveDirection) {
+        self.engine.move_side(direction);
+    }
+
+    fn hold(&mut self) {
+        self.engine.hold();
+        self.track(None);
+    }
+
+    fn tick_clear(&mut self, elapsed: Duration) {
+        self.engine.tick_clear(elapsed);
+        self.track(None);
+    }
+
+    fn tick_entry(&mut self, elapsed: Duration) {
+        if self.engine.tick_entry(elapsed) {
+            self.track(None);
+        }
+    }
+
+    fn tick_lock(&mut self, elapsed: Duration) {
+        let result = self.engine.tick_lock(elapsed);
+        self.track(result);
+    }
+
+    /// Position centering the block in a preview slot starting at `left`
+    fn preview_pos(block: &TetrisBlock, left: i32, width: i32, height: i32) -> (i32, i32) {
+        (
+            left + width / 2 - block.pattern.len() as i32 / 2,
+            height / 2 - block.pattern[0].len() as i32 / 2,
+        )
+    }
+
+    /// Lays upcoming pieces side by side in a strip, the first one on the left,
+    /// or one under another in a column, the first one on top. Pieces without room are left out
+    fn stack_previews(
+        blocks: &mut VecDeque<TetrisBlock>,
+        width: i32,
+        height: i32,
+        panel: NextPanel,
+    ) {
+        match panel {
+            NextPanel::Strip => {
+                blocks.truncate((width / 5).max(1) as usize);
+                let slot = width / blocks.len().max(1) as i32;
+                for (i, block) in blocks.iter_mut().enumerate() {
+                    block.pos = Self::preview_pos(block, i as i32 * slot, slot, height);
+                }
+            }
+            NextPanel::Column => {
+                blocks.truncate((height / 6).max(1) as usize);
+                let slot = height / blocks.len().max(1) as i32;
+                for (i, block) in blocks.iter_mut().enumerate() {
+                    let (x, y) = Self::preview_pos(block, 0, width, slot);
+                    // canvas y grows upwards
+                    block.pos = (x, y + height - (i as i32 + 1) * slot);
+                }
+            }
+        }
+    }
+
+    /// Shadow is shown when the piece is within configured distance from landing, 0 means always
+    fn ghost_visible(&self, end_pos: (i32, i32)) -> bool {
+        let distance = self.config.ghost_distance;
+        distance == 0 || ((end_pos.0 - self.engine.current_block.pos.0) as usize) <= distance
+    }
+
+    /// Shadow color, with `ghost_gradient` the piece's own color fading into the background
+    /// the farther the piece is from landing
+    fn ghost_color(&self, end_pos: (i32, i32)) -> Color {
+        if !self.config.ghost_gradient || self.color_mode != ColorMode::Full {
+            return self.theme.ghost;
+        }
+        let current = &self.engine.current_block;
+        let distance = (end_pos.0 - current.pos.0) as f64 / self.engine.game_width as f64;
+        // never as bright as the piece itself, nor lost in the background
+        blend(
+            Color::Indexed(current.color),
+            self.theme.background,
+            0.25 + 0.5 * distance,
+        )
+    }
+
+    /// For each cell of the pattern at `pos` tells if it rests on the floor, the stack or the piece itself
+    fn ghost_support(&self, pos: (i32, i32)) -> Vec<Vec<bool>> {
+        let engine = &self.engine;
+        let (x, y) = (pos.0 as usize, pos.1 as usize);
+        let pattern = &engine.current_block.pattern;
+        pattern
+            .iter()
+            .enumerate()
+            .map(|(i, col)| {
+                col.iter()
+                    .enumerate()
+                    .map(|(j, _)| {
+                        x + i + 1 >= engine.game_width
+                            || engine
+                                .cell_y((y + j) as i32)
+                                .is_some_and(|y| engine.filled_area[x + i + 1][y] != EMPTY)
+                            || pattern.get(i + 1).and_then(|c| c.get(j)) == Some(&true)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Cell borders a differently colored settled cell before it on either axis,
+    /// so each boundary between pieces gets a single darkened side
+    fn is_outline(&self, x: usize, y: usize) -> bool {
+        let board = &self.engine.filled_area;
+        let color = board[x][y];
+        let differs = |other: Option<u8>| other != EMPTY && other != color;
+        (x > 0 && differs(board[x - 1][y])) || (y > 0 && differs(board[x][y - 1]))
+    }
+
+    /// Area at the spawn edge the next piece comes in at, marked with `spawn_guide`,
+    /// none when the guide is off
+    fn spawn_guide_cells(&self) -> Vec<(usize, usize)> {
+        if !self.config.spawn_guide {
+            return Vec::new();
+        }
+        let engine = &self.engine;
+        let pattern = &engine.next_block().pattern;
+        let height = pattern.iter().map(|col| col.len()).max().unwrap_or(0);
+        let start_y = engine.next_spawn_y();
+        let end_y = (start_y + height).min(engine.game_height);
+        (0..pattern.len())
+            .flat_map(|x| (start_y..end_y).map(move |y| (x, y)))
+            .collect()
+    }
+
+    /// Faint lines along the gravity axis every few cells, and the lanes the current piece
+    /// falls down highlighted. Drawn first, so pieces and the shadow cover them
+    fn paint_grid(&self, painter: &mut Painter) {
+        let engine = &self.engine;
+        let (x, y) = engine.current_block.pos;
+        let mut lanes = vec![false; engine.game_height];
+        for col in &engine.current_block.pattern {
+            for (j, filled) in col.iter().enumerate() {
+                if let Some(lane) = engine.cell_y(y + j as i32).filter(|_| *filled) {
+                    lanes[lane] = true;
+                }
+            }
+        }
+        let start = x.max(0) as usize;
+        for (lane, falling) in lanes.into_iter().enumerate() {
+            let (color, from) = match falling {
+                true => (Color::Indexed(240), start),
+                false if lane % GRID_SPACING == GRID_SPACING - 1 => (Color::Indexed(238), 0),
+                false => continue,
+            };
+            for x in from..engine.game_width {
+                let (x, y) = self.to_screen(x, lane);
+                if let Some((x, y)) = painter.get_point(x, y) {
+                    painter.paint(x, y, self.color_mode.map(color));
+                }
+            }
+        }
+    }
+
+    /// Empty board cells touching the falling piece, diagonals included. Cells of the stack
+    /// and anything past the board edges are left out, so the outline only marks free space
+    fn outline_cells(&self) -> Vec<(usize, usize)> {
+        let engine = &self.engine;
+        let block = &engine.current_block;
+        let (x, y) = block.pos;
+        let filled = |i: i32, j: i32| {
+            i >= 0
+                && j >= 0
+                && block
+                    .pattern
+                    .get(i as usize)
+                    .and_then(|col| col.get(j as usize))
+                    .copied()
+                    .unwrap_or(false)
+        };
+        let mut cells = Vec::new();
+        for (i, col) in block.pattern.iter().enumerate() {
+            for (j, _) in col.iter().enumerate().filter(|(_, filled)| **filled) {
+                for (di, dj) in (-1..=1).flat_map(|di| (-1..=1).map(move |dj| (di, dj))) {
+                    let (i, j) = (i as i32 + di, j as i32 + dj);
+                    if filled(i, j) {
+                        continue;
+                    }
+                    let cell_x = x + i;
+                    if cell_x < 0 || cell_x >= engine.game_width as i32 {
+                        continue;
+                    }
+                    let Some(cell_y) = engine.cell_y(y + j) else {
+                        continue;
+                    };
+                    let cell = (cell_x as usize, cell_y);
+                    if engine.filled_area[cell.0][cell.1] == EMPTY && !cells.contains(&cell) {
+                        cells.push(cell);
+                    }
+                }
+            }
+        }
+        cells
+    }
+
+    fn is_vertical(&self) -> bool {
+        self.config.orientation == Orientation::Vertical
+    }
+
+    /// Kind of piece a locked cell of given color came from, unknown with random colors
+    fn cell_kind(&self, color: u8) -> Option<PieceKind> {
+        let custom = &self.engine.rules.pieces;
+        if !custom.is_empty() {
+            let index = custom.iter().position(|piece| piece.color == Some(color))?;
+            return Some(PieceKind::Custom(index as u8));
+        }
+        let index = self
+            .theme
+            .pieces?
+            .iter()
+            .position(|piece| *piece == color)?;
+        Some(PieceKind::ALL[index])
+    }
+
+    /// Canvas points the block is painted at, with its copy shifted across the edge in wrap mode
+    fn block_points(&self, painter: &mut Painter, block: &TetrisBlock) -> Vec<(usize, usize)> {
+        let mut blocks = vec![block.clone()];
+        if self.config.wrap {
+            let mut shifted = block.clone();
+            shifted.pos.1 -= self.engine.game_height as i32;
+            blocks.push(shifted);
+        }
+        let mut points = Vec::new();
+        for block in blocks {
+            let block = match self.is_vertical() {
+                true => self.upright(&block),
+                false => block,
+            };
+            for (i, col) in block.pattern.iter().enumerate() {
+                for (j, _) in col.iter().enumerate().filter(|(_, filled)| **filled) {
+                    let x = i as f64 + block.pos.0 as f64;
+                    let y = j as f64 + block.pos.1 as f64;
+                    points.extend(painter.get_point(x, y));
+                }
+            }
+        }
+        points
+    }
+
+    /// Writes glyphs over the canvas points they mark, dark on the piece's color.
+    /// A terminal cell holds two points, or at scale 2 a point takes two cells of a row
+    fn render_glyphs(
+        &self,
+        glyphs: Vec<(usize, usize, char, Color)>,
+        area: Rect,
+        buf: &mut Buffer,
+    ) {
+        for (x, y, glyph, color) in glyphs {
+            let (x, y) = match self.scale {
+                2 => (x as u16 * 2, y as u16),
+                _ => (x as u16, y as u16 / 2),
+            };
+            for dx in 0..self.scale {
+                if let Some(cell) = buf.cell_mut((area.x + x + dx, area.y + y)) {
+                    cell.set_char(glyph).set_fg(Color::Black).set_bg(color);
+                }
+            }
+        }
+    }
+
+    /// Screen point of a board cell, on a vertical board the spawn edge is on top
+    fn to_screen(&self, x: usize, y: usize) -> (f64, f64) {
+        if self.is_vertical() {
+            (y as f64, (self.engine.game_width - 1 - x) as f64)
+        } else {
+            (x as f64, y as f64)
+        }
+    }
+
+    /// Block as drawn on a vertical board, with the board's gravity pointing down
+    fn upright(&self, block: &TetrisBlock) -> TetrisBlock {
+        let width = block.pattern.len();
+        let height = block.pattern.iter().map(|x| x.len()).max().unwrap_or(0);
+        let mut pattern = vec![vec![false; width]; height];
+        for (i, col) in block.pattern.iter().enumerate() {
+            for (j, filled) in col.iter().enumerate() {
+                pattern[j][width - 1 - i] = *filled;
+            }
+        }
+        TetrisBlock {
+            pattern,
+            pos: (
+                block.pos.1,
+                self.engine.game_width as i32 - block.pos.0 - width as i32,
+            ),
+            ..block.clone()
+        }
+    }
+
+    /// Block turned like the board in its preview slot, staying around the same center
+    fn upright_preview(&self, block: &TetrisBlock) -> TetrisBlock {
+        if !self.is_vertical() {
+            return block.clone();
+        }
+        let width = block.pattern.len() as i32;
+        let height = block.pattern.iter().map(|x| x.len()).max().unwrap_or(0) as i32;
+        TetrisBlock {
+            pattern: self.upright(block).pattern,
+            pos: (
+                block.pos.0 + width / 2 - height / 2,
+                block.pos.1 + height / 2 - width / 2,
+            ),
+            ..block.clone()
+        }
+    }
+
+    /// Canvas filling the panel with blocks at their preview positions, used for upcoming
+    /// and held pieces. Dimmed blocks are drawn in the shadow color
+    fn preview<'a>(
+        &'a self,
+        block: Block<'a>,
+        shown: Vec<TetrisBlock>,
+        area: Rect,
+        dimmed: bool,
+    ) -> Canvas<'a, impl Fn(&mut Context) + 'a> {
+        Canvas::default()
+            .block(block)
+            .background_color(self.color_mode.map(self.theme.background))
+            .marker(ratatui::symbols::Marker::HalfBlock)
+            .x_bounds([-1.0, area.width as f64 - 3.0])
+            .y_bounds([0.0, (area.height as f64 - 2.0) * 2.0])
+            .paint(move |ctx| {
+                ctx.layer();
+                for shown in &shown {
+                    let color = match dimmed {
+                        true => self.theme.ghost,
+                        false => Color::Indexed(shown.color),
+                    };
+                    ctx.draw(&self.colored(&self.upright_preview(shown), color));
+                }
+            })
+    }
+
+    /// Lines cleared towards the next level, in the room left after the round number
+    fn render_level_progress(&self, area: Rect, round_width: u16, buf: &mut Buffer) {
+        let inner = Block::bordered().inner(area);
+        let x = round_width + 2;
+        if inner.width < x + 10 {
+            return;
+        }
+        let lines = self.engine.lines % LINES_PER_LEVEL;
+        Gauge::default()
+            .gauge_style(Style::new().magenta().on_dark_gray())
+            .ratio(lines as f64 / LINES_PER_LEVEL as f64)
+            .label(format!("{lines}/{LINES_PER_LEVEL}"))
+            .render(
+                Rect {
+                    x: inner.x + x,
+                    y: inner.y + 1,
+                    width: inner.width - x - 1,
+                    height: 1,
+                },
+                buf,
+            );
+    }
+
+    /// Time until the next gravity step, in the room left after the game state
+    fn render_gravity_bar(&self, area: Rect, state_width: u16, buf: &mut Buffer) {
+        let inner = Block::bordered().inner(area);
+        let x = state_width + 2;
+        if inner.width < x + 5 {
+            return;
+        }
+        let ratio = self.gravity_elapsed.as_secs_f64() / self.gravity_interval().as_secs_f64();
+        Gauge::default()
+            .gauge_style(Style::new().cyan().on_dark_gray())
+            .ratio(ratio.clamp(0.0, 1.0))
+            .label("")
+            .render(
+                Rect {
+                    x: inner.x + x,
+                    y: inner.y + 2,
+                    width: inner.width - x - 1,
+                    height: 1,
+                },
+                buf,
+            );
+    }
+
+    /// Progress towards the goal of the mode, the score in most of them
+    fn goal_line(&self) -> text::Line<'static> {
+        match self.engine.rules.mode {
+            GameMode::Sprint { lines } => text::Line::from(vec![
+                " Time: ".white(),
+                format_time(self.play_time()).bold().green(),
+                "  Lines: ".white(),
+                format!("{}/{lines}", self.engine.lines).bold().magenta(),
+            ]),
+            GameMode::Ultra { duration } => text::Line::from(vec![
+                " Score: ".white(),
+                self.format_number(self.engine.points).bold().green(),
+                "  Left: ".white(),
+                format_time(duration.saturating_sub(self.play_time()))
+                    .bold()
+                    .magenta(),
+            ]),
+            GameMode::Endless | GameMode::Garbage => text::Line::from(vec![
+                " Score: ".white(),
+                self.format_number(self.engine.points).bold().green(),
+                "  Level: ".white(),
+                self.engine.level.to_string().bold().magenta(),
+            ]),
+            GameMode::Marathon { level } => text::Line::from(vec![
+                " Score: ".white(),
+                self.format_number(self.engine.points).bold().green(),
+                "  Level: ".white(),
+                format!("{}/{level}", self.engine.level).bold().magenta(),
+            ]),
+            GameMode::Zen => text::Line::from(vec![
+                " Score: ".white(),
+                self.format_number(self.engine.points).bold().green(),
+                "  Lines: ".white(),
+                self.engine.lines.to_string().bold().magenta(),
+            ]),
+        }
+    }
+
+    /// Name and color a piece is shown with, custom pieces have their own
+    fn piece_label(&self, kind: PieceKind) -> (String, Color) {
+        let (name, color) = match (kind, self.theme.pieces) {
+            (PieceKind::Custom(index), _) => {
+                let piece = &self.engine.rules.pieces[index as usize];
+                (piece.name.clone(), piece.color)
+            }
+            (kind, colors) => (kind.to_string(), colors.map(|colors| colors[kind.index()])),
+        };
+        (
+            name,
+            self.color_mode
+                .map(color.map_or(Color::White, Color::Indexed)),
+        )
+    }
+
+    /// Goal of the mode, upcoming and held pieces in a single line, the status message after them
+    fn render_compact_hud(&self, area: Rect, buf: &mut Buffer) {
+        let mut line = self.goal_line();
+        if self.config.next_panel == NextPanel::Strip {
+            line.push_span("  Next:".white());
+            for block in &self.engine.next_blocks {
+                let (name, color) = self.piece_label(block.kind);
+                line.push_span(format!(" {name}").fg(color).bold());
+            }
+        }
+        line.push_span("  Hold: ".white());
+        let hold_used = self.engine.hold_used;
+        line.push_span(match &self.engine.hold_block {
+            // held piece is muted until the next lock makes hold available again
+            Some(held) if hold_used && self.config.hold_indicator == HoldIndicator::Dim => {
+                self.piece_label(held.kind).0.dark_gray()
+            }
+            Some(held) => {
+                let (name, color) = self.piece_label(held.kind);
+                name.fg(color).bold()
+            }
+            None => "-".dark_gray(),
+        });
+        if hold_used && self.config.hold_indicator == HoldIndicator::Glyph {
+            line.push_span(" ✕".red().bold());
+        }
+        if let Some(status) = &self.status {
+            line.push_span(format!("  {status}").yellow());
+        }
+        Paragraph::new(line).render(area, buf);
+    }
+
+    /// Hold, info, stats and piece count panels under the board
+    fn render_panels(&self, buf: &mut Buffer) {
+        let mut hold_block = Block::bordered()
+            .bold()
+            .title_top(" Hold ".bold().green())
+            .title_alignment(Alignment::Center);
+        if self.show_help {
+            hold_block = hold_block.title_bottom(" <H> ".bold().blue());
+        }
+        let hold_used = self.engine.hold_used;
+        if hold_used && self.config.hold_indicator == HoldIndicator::Glyph {
+            hold_block = hold_block.title_top(text::Line::from("✕".red().bold()).right_aligned());
+        }
+        let hold_inner = Block::bordered().inner(self.hold_rect);
+        let held = self.engine.hold_block.clone().map(|mut held| {
+            held.pos = Tetris::preview_pos(
+                &held,
+                0,
+                hold_inner.width as i32,
+                hold_inner.height as i32 * 2,
+            );
+            held
+        });
+        // held piece is muted until the next lock makes hold available again
+        self.preview(
+            hold_block,
+            held.into_iter().collect(),
+            self.hold_rect,
+            hold_used && self.config.hold_indicator == HoldIndicator::Dim,
+        )
+        .render(self.hold_rect, buf);
+
+        let shortcut_info = if !self.show_help {
+            if self.engine.game_state == GameState::Finished {
+                " <R>".bold().blue() + " Reset ".not_bold().white()
+            } else {
+                text::Line::default()
+            }
+        } else if self.engine.game_state == GameState::Finished {
+            " <Ctrl + C>".bold().blue()
+                + " Exit ".not_bold().white()
+                + "<R>".bold().blue()
+                + " Reset ".not_bold().white()
+        } else {
+            " <Ctrl + C>".bold().blue()
+                + " Exit ".not_bold().white()
+                + "<P>".bold().blue()
+                + " Pause ".not_bold().white()
+        };
+
+        let round_line = text::Line::from(if self.config.mercy_count > 0 {
+            vec![
+                " Round: ".white(),
+                self.format_number(self.engine.rounds).bold().blue(),
+                "  Mercy: ".white(),
+                self.engine.mercies_left.to_string().bold().yellow(),
+            ]
+        } else {
+            vec![
+                " Round: ".white(),
+                self.format_number(self.engine.rounds).bold().blue(),
+            ]
+        });
+        let round_width = round_line.width() as u16;
+        let state_line = match &self.status {
+            Some(status) => text::Line::from(format!(" {status}").yellow()),
+            None => {
+                let mut line = text::Line::from(vec![
+                    " State: ".white(),
+                    match self.engine.game_state {
+                        GameState::Starting => "Starting".to_string().green(),
+                        GameState::Playing => "Playing".to_string().green(),
+                        GameState::Paused => "Paused".to_string().yellow().bold(),
+                        GameState::Finished => "Finished".to_string().red().bold(),
+                    },
+                ]);
+                if self.engine.combo > 0 {
+                    line.push_span("  Combo ".white());
+                    line.push_span(self.engine.combo.to_string().bold().cyan());
+                }
+                if self.engine.back_to_back {
+                    line.push_span("  B2B".bold().magenta());
+                }
+                line
+            }
+        };
+        let state_width = state_line.width() as u16;
+        let info = Paragraph::new(Text::from(vec![self.goal_line(), round_line, state_line]))
+            .block(
+                Block::bordered()
+                    .title_top(match &self.daily {
+                        Some(daily) => format!(" Daily {} #{} ", daily.date, self.engine.seed)
+                            .bold()
+                            .green(),
+                        None => " Info ".bold().green(),
+                    })
+                    .title_bottom(shortcut_info)
+                    .title_alignment(Alignment::Center),
+            );
+
+        info.render(self.info_rect[0], buf);
+        if matches!(
+            self.engine.rules.mode,
+            GameMode::Endless | GameMode::Garbage | GameMode::Marathon { .. }
+        ) {
+            self.render_level_progress(self.info_rect[0], round_width, buf);
+        }
+        let falling = matches!(
+            self.engine.game_state,
+            GameState::Playing | GameState::Paused
+        );
+        if self.config.gravity_bar && falling && self.gravity_enabled {
+            self.render_gravity_bar(self.info_rect[0], state_width, buf);
+        }
+
+        if let Some(stats_rect) = self.info_rect.get(1) {
+            self.render_stats(*stats_rect, buf);
+        }
+        if let Some(pieces_rect) = self.info_rect.get(2) {
+            self.render_piece_counts(*pieces_rect, buf);
+        }
+    }
+
+    /// Lines by clear type, pieces and their rate, and play time
+    fn render_stats(&self, area: Rect, buf: &mut Buffer) {
+        let engine = &self.engine;
+        let time = self.play_time();
+        let pieces_per_second = engine.rounds as f64 / time.as_secs_f64().max(1.0);
+        let [singles, doubles, triples, tetrises] = engine.clears;
+        // the count flashes red for a moment after each fault
+        let faults = engine.finesse_faults.to_string().bold();
+        let faults = match self.finesse_fault_at {
+            Some(time) if time.elapsed() < FINESSE_FLASH => faults.red(),
+            _ => faults.blue(),
+        };
+        Paragraph::new(Text::from(vec![
+            text::Line::from(vec![
+                " Lines: ".white(),
+                engine.lines.to_string().bold().blue(),
+                "  Time: ".white(),
+                format!("{:02}:{:02}", time.as_secs() / 60, time.as_secs() % 60)
+                    .bold()
+                    .blue(),
+            ]),
+            text::Line::from(vec![
+                " Pieces: ".white(),
+                engine.rounds.to_string().bold().blue(),
+                "  PPS: ".white(),
+                format!("{pieces_per_second:.2}").bold().blue(),
+            ]),
+            text::Line::from(vec![
+                " 1-4x: ".white(),
+                format!("{singles} {doubles} {triples} {tetrises}")
+                    .bold()
+                    .blue(),
+                "  Faults: ".white(),
+                faults,
+            ]),
+        ]))
+        .block(
+            Block::bordered()
+                .title_top(" Stats ".bold().green())
+                .title_alignment(Alignment::Center),
+        )
+        .render(area, buf);
+    }
+
+    /// How many pieces of each kind were dealt, in the colors of the theme
+    fn render_piece_counts(&self, area: Rect, buf: &mut Buffer) {
+        let spans: Vec<_> = self
+            .engine
+            .kinds()
+            .into_iter()
+            .map(|kind| {
+                let (name, color) = self.piece_label(kind);
+                let count = self.engine.spawned[kind.index()];
+                vec![
+                    format!(" {name}").fg(color).bold(),
+                    format!(" {count:<3}").white(),
+                ]
+            })
+            .collect();
+        Paragraph::new(Text::from(
+            spans
+                .chunks(3)
+                .map(|line| text::Line::from(line.concat()))
+                .collect::<Vec<_>>(),
+        ))
+        .block(
+            Block::bordered()
+                .title_top(" Pieces ".bold().green())
+                .title_alignment(Alignment::Center),
+        )
+        .render(area, buf);
+    }
+
+    /// Dims the board and shows final stats in a box in its middle
+    fn render_game_over(&self, area: Rect, buf: &mut Buffer) {
+        let engine = &self.engine;
+        let stat = |name: &'static str, value: u64| {
+            text::Line::from(vec![
+                format!(" {name:<8}").white(),
+                self.format_number(value).bold().green(),
+            ])
+        };
+        let mut lines = vec![
+            stat("Score:", engine.points),
+            stat("Rounds:", engine.rounds),
+            stat("Level:", engine.level),
+            stat("Lines:", engine.lines),
+            text::Line::default(),
+            "<R>".bold().blue()
+                + " Restart ".not_bold().white()
+                + "<Ctrl + C>".bold().blue()
+                + " Exit".not_bold().white(),
+        ];
+
+        match engine.rules.mode {
+            GameMode::Sprint { .. } if engine.won => {
+                // finishing time is the result of a sprint
+                lines.insert(
+                    0,
+                    text::Line::from(vec![
+                        " Time:   ".white(),
+                        format_time(self.play_time()).bold().yellow(),
+                    ]),
+                );
+                self.render_overlay(area, buf, " Complete ", Color::Green, lines);
+            }
+            GameMode::Ultra { .. } if engine.won => {
+                self.render_overlay(area, buf, " Time's up ", Color::Green, lines);
+            }
+            GameMode::Marathon { .. } if engine.won => {
+                self.render_overlay(area, buf, " Victory ", Color::Green, lines);
+            }
+            _ => self.render_overlay(area, buf, " Game over ", Color::Red, lines),
+        }
+    }
+
+    fn render_countdown(&self, area: Rect, buf: &mut Buffer) {
+        let step = (self.countdown_elapsed.as_secs() as usize).min(COUNTDOWN.len() - 1);
+        let lines = vec![text::Line::from(
+            format!("  {}  ", COUNTDOWN[step]).bold().yellow(),
+        )];
+        self.render_overlay(area, buf, " Get ready ", Color::Yellow, lines);
+    }
+
+    /// Menu shown while paused, selected option is highlighted
+    fn render_pause_menu(&self, area: Rect, buf: &mut Buffer) {
+        let mut lines: Vec<text::Line> = PAUSE_MENU
+            .iter()
+            .enumerate()
+            .map(|(i, option)| {
+                if i == self.pause_selection {
+                    format!("> {option} <").bold().black().on_yellow().into()
+                } else {
+                    format!("  {option}  ").white().into()
+                }
+            })
+            .collect();
+        let (back, forward) = self.engine.history();
+        if back + forward > 0 {
+            lines.push(text::Line::default());
+            lines.push(format!("Piece {}/{}", back, back + forward).white().into());
+        }
+        if self.show_help {
+            lines.push(text::Line::default());
+            lines.push(
+                "<↑/↓>".bold().blue()
+                    + " Select ".not_bold().white()
+                    + "<Enter>".bold().blue()
+                    + " Confirm".not_bold().white(),
+            );
+            lines.push("<←/→>".bold().blue() + " Step through the history".not_bold().white());
+        }
+
+        self.render_overlay(area, buf, " Paused ", Color::Yellow, lines);
+    }
+
+    /// Dims the area and draws the lines in a box in its middle
+    fn render_overlay(
+        &self,
+        area: Rect,
+        buf: &mut Buffer,
+        title: &str,
+        color: Color,
+        lines: Vec<text::Line>,
+    ) {
+        let inner = area.inner(ratatui::layout::Margin::new(1, 1));
+        buf.set_style(inner, Style::new().add_modifier(Modifier::DIM));
+
+        let width =
+            (lines.iter().map(|line| line.width()).max().unwrap_or(0) as u16 + 4).min(inner.width);
+        let height = (lines.len() as u16 + 2).min(inner.height);
+        let overlay = Rect {
+            x: inner.x + (inner.width - width) / 2,
+            y: inner.y + (inner.height - height) / 2,
+            width,
+            height,
+        };
+
+        Clear.render(overlay, buf);
+        Paragraph::new(lines)
+            .alignment(Alignment::Center)
+            .block(
+                Block::bordered()
+                    .bold()
+                    .fg(color)
+                    .title_top(title.bold().fg(color))
+                    .title_alignment(Alignment::Center),
+            )
+            .render(overlay, buf);
+    }
+
+    /// Block in given color, mapped to one the terminal can show
+    fn colored(&self, block: &TetrisBlock, color: Color) -> BlockShape {
+        BlockShape {
+            block: block.clone(),
+            color: self.color_mode.map(color),
+        }
+    }
+
+    /// Right aligns the number to the configured width, longer numbers are abbreviated
+    fn format_number(&self, number: u64) -> String {
+        let width = self.config.number_width;
+        let mut text = number.to_string();
+        if text.len() > width {
+            let short = abbreviate(number);
+            if short.len() < text.len() {
+                text = short;
+            }
+        }
+        format!("{text:>width$}")
+    }
+
+    /// Screen size with the board cells drawn at given scale, panels around the board keep their size
+    fn screen_size(&self, scale: u16) -> (u16, u16) {
+        let inner = Block::bordered().inner(self.board_rect);
+        let board_width = inner.width / self.scale * scale + 2;
+        let board_height = inner.height / self.scale * scale + 2;
+        let side_width = self.screen_rect.width - self.board_rect.width;
+        let footer_height = self.screen_rect.height - self.board_rect.height;
+        (board_width + side_width, board_height + footer_height)
+    }
+
+    /// Resizes the board to given scale, moving the panels under it
+    fn set_scale(&mut self, scale: u16) {
+        if scale == self.scale {
+            return;
+        }
+        let (width, height) = self.screen_size(scale);
+        let board_height = height - (self.screen_rect.height - self.board_rect.height);
+        let (old_width, old_height) = (self.board_rect.width, self.board_rect.height);
+        self.board_rect.width = width - (self.screen_rect.width - self.board_rect.width);
+        self.board_rect.height = board_height;
+        self.field_rect.width = self.field_rect.width - old_width + self.board_rect.width;
+        self.field_rect.height = self.field_rect.height - old_height + board_height;
+        self.screen_rect.width = width;
+        self.screen_rect.height = height;
+        for rect in [&mut self.hold_rect]
+            .into_iter()
+            .chain(self.info_rect.iter_mut())
+        {
+            rect.y = rect.y + board_height - old_height;
+        }
+        self.next_rect = Layout::next_rect(self.screen_rect, self.compact, &self.config);
+        self.scale = scale;
+    }
+
+    /// Draws the widget in half of the area, then blows every cell up to 2x2 cells.
+    /// Each half of a half block cell becomes a whole row
+    fn render_scaled(widget: impl Widget, area: Rect, buf: &mut Buffer) {
+        let mut small = Buffer::empty(Rect::new(0, 0, area.width / 2, area.height / 2));
+        widget.render(small.area, &mut small);
+        for y in 0..small.area.height {
+            for x in 0..small.area.width {
+                let cell = &small[(x, y)];
+                let (top, bottom) = match cell.symbol() {
+                    "▀" => (true, false),
+                    "▄" => (false, true),
+                    "█" => (true, true),
+                    _ => (false, false),
+                };
+                for (dy, filled) in [(0, top), (1, bottom)] {
+                    for dx in 0..2 {
+                        let position = (area.x + x * 2 + dx, area.y + y * 2 + dy);
+                        if let Some(big) = buf.cell_mut(position) {
+                            big.set_symbol(if filled { "█" } else { " " })
+                                .set_fg(cell.fg)
+                                .set_bg(cell.bg);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Widget for &mut Tetris {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        // big board falls back to the normal one when the terminal is too small for it
+        let (big_width, big_height) = self.screen_size(2);
+        let big_fits = buf.area.width >= big_width && buf.area.height >= big_height;
+        self.set_scale(if self.big && big_fits { 2 } else { 1 });
+
+        let last_point_exists = buf
+            .cell((self.screen_rect.width - 1, self.screen_rect.height - 1))
+            .is_some();
+
+        self.dirty = false;
+        self.engine.changed = false;
+
+        if last_point_exists {
+            self.locked = false;
+            // a strip of upcoming pieces is part of the compact HUD
+            if !self.next_rect.is_empty() {
+                let mut next_block = Block::bordered()
+                    .bold()
+                    .title_top(" Next ".bold().green())
+                    .title_alignment(Alignment::Center);
+                if self.show_help {
+                    next_block = next_block.title_bottom(" <Space> ".bold().blue());
+                }
+                let next_inner = Block::bordered().inner(self.next_rect);
+                let mut next_blocks = self.engine.next_blocks.clone();
+                Tetris::stack_previews(
+                    &mut next_blocks,
+                    next_inner.width as i32,
+                    next_inner.height as i32 * 2,
+                    self.config.next_panel,
+                );
+                self.preview(next_block, next_blocks.into(), self.next_rect, false)
+                    .render(self.next_rect, buf);
+            }
+
+            if self.compact {
+                self.render_compact_hud(self.info_rect[0], buf);
+            } else {
+                self.render_panels(buf);
+            }
+
+            // flashing needs redraws, quiet mode keeps the warning steady
+            let flash_on = self.config.quiet
+                || (self.start_time.elapsed().as_millis() / 250).is_multiple_of(2);
+            let border = match self.in_danger() && flash_on {
+                true => Color::Red,
+                false => self.theme.border,
+            };
+            // margins around a standard board, left blank without colors
+            if self.field_rect != self.board_rect && self.color_mode != ColorMode::Mono {
+                buf.set_style(
+                    self.field_rect,
+                    Style::new().bg(self.color_mode.map(Color::Indexed(235))),
+                );
+                buf.set_style(self.board_rect, Style::new().bg(Color::Reset));
+            }
+            let mut board_block = Block::bordered()
+                .bold()
+                .fg(self.color_mode.map(border))
+                .title_top(" Tetris ".bold().green())
+                .title_bottom(if self.show_help {
+                    let (rotate, moves) = if self.is_vertical() {
+                        (" <W/↑>", "<A/←, D/→, S/↓>")
+                    } else {
+                        (" <A/←>", "<W/↑, S/↓, D/→>")
+                    };
+                    rotate.bold().blue()
+                        + " Rotate ".white().not_bold()
+                        + moves.bold().blue()
+                        + " Move ".white().not_bold()
+                        + "<?>".bold().blue()
+                        + " Hide help ".white().not_bold()
+                } else {
+                    " <?>".bold().blue() + " Help ".white().not_bold()
+                })
+                .title_alignment(Alignment::Center);
+            if !self.show_ghost {
+                // reminder that the shadow was turned off
+                board_block = board_block.title_top(
+                    text::Line::from(" <G> Shadow off ".dark_gray().not_bold()).left_aligned(),
+                );
+            }
+            if !self.gravity_enabled {
+                board_block = board_block.title_top(
+                    text::Line::from(" <F> Gravity off ".dark_gray().not_bold()).right_aligned(),
+                );
+            }
+
+            // points of the pieces' cells and their glyphs, written over the drawn board
+            let glyphs = RefCell::new(Vec::new());
+            let board = Canvas::default()
+                .background_color(self.color_mode.map(self.theme.background))
+                .marker(ratatui::symbols::Marker::HalfBlock)
+                .x_bounds(if self.is_vertical() {
+                    [-1.0, self.engine.game_height as f64 - 1.0]
+                } else {
+                    [-1.0, self.engine.game_width as f64 - 1.0]
+                })
+                .y_bounds(if self.is_vertical() {
+                    [0.0, self.engine.game_width as f64]
+                } else {
+                    [0.0, self.engine.game_height as f64]
+                })
+                .paint(|ctx| {
+                    ctx.layer();
+                    let engine = &self.engine;
+                    let mut glyphs = glyphs.borrow_mut();
+
+                    let mut painter = Painter::from(&mut *ctx);
+                    if self.show_grid {
+                        self.paint_grid(&mut painter);
+                    }
+                    for (x, y) in self.spawn_guide_cells() {
+                        let (x, y) = self.to_screen(x, y);
+                        if let Some((x, y)) = painter.get_point(x, y) {
+                            painter.paint(x, y, self.color_mode.map(Color::Indexed(238)));
+                        }
+                    }
+                    let play_time = self.play_time();
+                    for (x, col) in engine.filled_area.iter().enumerate() {
+                        for (y, cell) in col.iter().enumerate() {
+                            if let Some(color) = *cell {
+                                let kind = self
+                                    .color_mode
+                                    .glyphs()
+                                    .then(|| self.cell_kind(color))
+                                    .flatten();
+                                let color = if engine.clearing.contains(&x) {
+                                    Color::White
+                                } else if self.config.piece_outlines && self.is_outline(x, y) {
+                                    darken(Color::Indexed(color))
+                                } else {
+                                    Color::Indexed(color)
+                                };
+                                let color = self.faded(color, engine.placed[x][y], play_time);
+                                let (x, y) = self.to_screen(x, y);
+                                if let Some((x, y)) = painter.get_point(x, y) {
+                                    painter.paint(x, y, self.color_mode.map(color));
+                                    if let Some(kind) = kind {
+                                        glyphs.push((
+                                            x,
+                                            y,
+                                            glyph(kind),
+                                            self.color_mode.map(color),
+                                        ));
+                                    }
                                 }
                             }
                         }
                     }
+                    if self.show_outline && !engine.is_between_pieces() {
+                        for (x, y) in self.outline_cells() {
+                            let (x, y) = self.to_screen(x, y);
+                            if let Some((x, y)) = painter.get_point(x, y) {
+                                painter.paint(x, y, self.color_mode.map(Color::White));
+                            }
+                        }
+                    }
+
+                    // in wrap mode pieces crossing the edge are drawn again shifted by the board size,
+                    // parts outside of canvas are skipped
+                    let wrap_shift = engine.game_height as i32;
+                    let draw_turned = |ctx: &mut Context, block: &TetrisBlock, color: Color| {
+                        if self.is_vertical() {
+                            ctx.draw(&self.colored(&self.upright(block), color));
+                        } else {
+                            ctx.draw(&self.colored(block, color));
+                        }
+                    };
+                    let draw_wrapped = |ctx: &mut Context, block: &TetrisBlock, color: Color| {
+                        draw_turned(ctx, block, color);
+                        if self.config.wrap {
+                            let mut shifted = block.clone();
+                            shifted.pos.1 -= wrap_shift;
+                            draw_turned(ctx, &shifted, color);
+                        }
+                    };
+
+                    // locked piece is already part of the board
+                    if engine.is_between_pieces() {
+                        return;
+                    }
+
+                    let current = &engine.current_block;
+                    let current_color = Color::Indexed(current.color);
+                    if self.color_mode.glyphs() {
+                        let mut painter = Painter::from(&mut *ctx);
+                        for (x, y) in self.block_points(&mut painter, current) {
+                            let color = self.color_mode.map(current_color);
+                            glyphs.push((x, y, glyph(current.kind), color));
+                        }
+                    }
+                    let end_pos = engine.get_end_move_pos();
+                    if self.config.quiet || !self.show_ghost || !self.ghost_visible(end_pos) {
+                        draw_wrapped(ctx, current, current_color);
+                        return;
+                    }
 
-                    let mut last_pos = self.current_block.clone();
-                    last_pos.pos = self.get_end_move_pos();
-                    last_pos.color = Color::DarkGray;
-                    ctx.draw(&last_pos);
+                    let mut last_pos = current.clone();
+                    last_pos.pos = end_pos;
+                    if self.config.ghost_support_tint {
+                        let support = self.ghost_support(last_pos.pos);
+                        let mut gap_pos = last_pos.clone();
+                        for (i, col) in support.iter().enumerate() {
+                            for (j, supported) in col.iter().enumerate() {
+                                last_pos.pattern[i][j] &= *supported;
+                                gap_pos.pattern[i][j] &= !*supported;
+                            }
+                        }
+                        draw_wrapped(ctx, &gap_pos, Color::Indexed(1));
+                    }
+                    draw_wrapped(ctx, &last_pos, self.ghost_color(end_pos));
 
-                    ctx.draw(&self.current_block);
+                    draw_wrapped(ctx, current, current_color);
                 });
 
-            board.render(self.board_rect, buf);
+            let board_inner = board_block.inner(self.board_rect);
+            board_block.render(self.board_rect, buf);
+            if self.scale == 2 {
+                Tetris::render_scaled(board, board_inner, buf);
+            } else {
+                board.render(board_inner, buf);
+            }
+            self.render_glyphs(glyphs.into_inner(), board_inner, buf);
+
+            match self.engine.game_state {
+                GameState::Finished => self.render_game_over(self.board_rect, buf),
+                GameState::Paused => self.render_pause_menu(self.board_rect, buf),
+                GameState::Starting => self.render_countdown(self.board_rect, buf),
+                GameState::Playing => {}
+            }
+            if self.pending_quit {
+                let lines = vec![text::Line::from(" Quit? (y/n) ".bold().white())];
+                self.render_overlay(self.board_rect, buf, " Quit ", Color::Yellow, lines);
+            }
+
+            #[cfg(feature = "debug-overlay")]
+            if self.debug.visible {
+                let block = &self.engine.current_block;
+                let state = vec![
+                    text::Line::from(format!(" State: {:?}", self.engine.game_state)),
+                    text::Line::from(format!(
+                        " Piece: {:?} turned {}",
+                        block.pos, block.orientation
+                    )),
+                    text::Line::from(format!(
+                        " Gravity: {}ms / {}ms",
+                        self.gravity_elapsed.as_millis(),
+                        self.engine.move_interval.as_millis()
+                    )),
+                ];
+                self.debug.render(state, self.board_rect, buf);
+            }
+
+            if let Some((action, time)) = self.input_echo {
+                let duration = Duration::from_millis(self.config.input_echo_ms);
+                let elapsed = time.elapsed();
+                if elapsed < duration && !self.config.quiet {
+                    let style = if elapsed < duration / 2 {
+                        Style::new().white().bold()
+                    } else {
+                        Style::new().dark_gray()
+                    };
+                    let text = format!(" {action} ");
+                    buf.set_string(
+                        self.board_rect.right() - 2 - text.len() as u16,
+                        self.board_rect.y + 1,
+                        text,
+                        style,
+                    );
+                } else {
+                    self.input_echo = None;
+                }
+            }
 
             // removes cursor from inside of the game
             // has to update each render to actually move cursor there
             // has to be rendered last on screen so there's cursor isn't left inside board after render
             // has to write 1 before last character on screen, so cursor going to next char doesn't go to next line
-            self.cursor_state = !self.cursor_state;
-            buf.cell_mut((self.screen_rect.width - 2, self.screen_rect.height - 1))
-                .unwrap()
-                .set_fg(if self.cursor_state {
-                    Color::Black
-                } else {
-                    Color::Reset
-                });
+            // skipped in quiet mode, as it changes a cell on every frame
+            if !self.config.quiet {
+                self.cursor_state = !self.cursor_state;
+                buf.cell_mut((self.screen_rect.width - 2, self.screen_rect.height - 1))
+                    .unwrap()
+                    .set_fg(if self.cursor_state {
+                        Color::Black
+                    } else {
+                        Color::Reset
+                    });
+            }
         } else {
             self.locked = true;
-            if self.game_state == GameState::Playing {
-                self.game_state = GameState::Paused;
+            if self.engine.game_state == GameState::Playing {
+                self.engine.game_state = GameState::Paused;
             }
 
             if area.height < 1 {
-                panic!("{}", area.height);
+                return;
             }
 
             if area.height > 0 {
@@ -603,3 +2886,891 @@ impl Widget for &mut Tetris {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tetris_engine::engine::GARBAGE;
+
+    fn tetris(config: Config) -> Tetris {
+        Tetris::new_with_seed(Rect::new(0, 0, 80, 20), config, KeyBindings::default(), 1)
+    }
+
+    fn press(tetris: &mut Tetris, code: KeyCode) {
+        tetris
+            .handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+    }
+
+    /// Renders the whole screen like a frame of the game loop
+    fn draw(tetris: &mut Tetris) -> Buffer {
+        let area = tetris.screen_rect;
+        let mut buf = Buffer::empty(area);
+        tetris.render(area, &mut buf);
+        buf
+    }
+
+    #[test]
+    fn quiet_mode_skips_frames_where_nothing_changed() {
+        for quiet in [false, true] {
+            let mut tetris = tetris(Config {
+                countdown: false,
+                quiet,
+                ..Config::default()
+            });
+            draw(&mut tetris);
+            // too short for gravity to move the piece
+            tetris.update(Duration::from_millis(1));
+            assert_eq!(tetris.needs_redraw(), !quiet);
+
+            tetris.update(tetris.engine.move_interval);
+            assert!(tetris.needs_redraw());
+        }
+    }
+
+    #[test]
+    fn hold_indicator_follows_hold_used() {
+        for indicator in [
+            HoldIndicator::Dim,
+            HoldIndicator::Glyph,
+            HoldIndicator::Hidden,
+        ] {
+            let mut tetris = tetris(Config {
+                countdown: false,
+                hold_indicator: indicator,
+                ..Config::default()
+            });
+            press(&mut tetris, KeyCode::Char('h'));
+            assert!(tetris.engine.hold_used);
+            let held = tetris.color_mode.map(Color::Indexed(
+                tetris.engine.hold_block.as_ref().unwrap().color,
+            ));
+            let ghost = tetris.color_mode.map(tetris.theme.ghost);
+
+            for hold_used in [true, false] {
+                tetris.engine.hold_used = hold_used;
+                let buf = draw(&mut tetris);
+                let area = tetris.hold_rect;
+                let inner = Block::bordered().inner(area);
+                let colors: Vec<Color> = inner
+                    .positions()
+                    .flat_map(|pos| {
+                        let cell = &buf[pos];
+                        [cell.fg, cell.bg]
+                    })
+                    .collect();
+                let dimmed = hold_used && indicator == HoldIndicator::Dim;
+                assert_eq!(colors.contains(&ghost), dimmed, "{indicator}");
+                assert_eq!(colors.contains(&held), !dimmed, "{indicator}");
+
+                let top: String = (area.left()..area.right())
+                    .map(|x| buf[(x, area.y)].symbol())
+                    .collect();
+                let marked = hold_used && indicator == HoldIndicator::Glyph;
+                assert_eq!(top.contains('✕'), marked, "{indicator}");
+            }
+        }
+    }
+
+    #[test]
+    fn lock_times_are_kept_only_for_cells_on_the_board() {
+        for fading in [false, true] {
+            let mut tetris = tetris(Config {
+                countdown: false,
+                fading,
+                ..Config::default()
+            });
+            // round count of a loaded save, far past anything worth a slot per round
+            tetris.engine.rounds = u64::MAX / 2;
+            press(&mut tetris, KeyCode::Char(' '));
+            let round = tetris.engine.rounds - 1;
+            assert_eq!(tetris.lock_times.contains_key(&round), fading);
+            assert_eq!(tetris.lock_times.len(), fading as usize);
+
+            // cells of the first piece are gone by the next lock
+            tetris.update(Duration::from_secs(1));
+            assert!(tetris.engine.clear_board());
+            press(&mut tetris, KeyCode::Char(' '));
+            assert_eq!(tetris.lock_times.len(), fading as usize);
+        }
+    }
+
+    #[test]
+    fn next_panel_fits_the_screen() {
+        let config = Config {
+            next_panel: NextPanel::Column,
+            ..Config::default()
+        };
+        let layout = Layout::new(Rect::new(0, 0, 80, 20), &config);
+        // column beside the board is cut to the board height, fitting four of five pieces
+        assert_eq!(layout.next, Rect::new(69, 0, 11, 15));
+        assert_eq!(layout.board.width, 69);
+        assert_eq!((layout.hold.x, layout.hold.y), (0, 15));
+
+        // strip too wide for the screen shrinks to the room left beside hold and info
+        let layout = Layout::new(Rect::new(0, 0, 60, 20), &Config::default());
+        assert_eq!(layout.next, Rect::new(0, 15, 14, 5));
+        assert_eq!(layout.hold.x, 14);
+        assert_eq!(layout.board.width, 60);
+    }
+
+    #[test]
+    fn standard_board_fits_every_screen_from_the_minimum_up() {
+        for orientation in [Orientation::Horizontal, Orientation::Vertical] {
+            for next_panel in [NextPanel::Strip, NextPanel::Column] {
+                let config = Config {
+                    playfield: Playfield::Standard,
+                    orientation,
+                    next_panel,
+                    ..Config::default()
+                };
+                let (width, min_height) = Tetris::min_size(&config);
+                for height in min_height..=20 {
+                    let layout = Layout::new(Rect::new(0, 0, width, height), &config);
+                    assert!(
+                        layout.field.contains(layout.board.as_position())
+                            && layout.board.bottom() <= layout.field.bottom()
+                            && layout.board.right() <= layout.field.right(),
+                        "{orientation:?} {next_panel:?} {height}: {:?} in {:?}",
+                        layout.board,
+                        layout.field
+                    );
+                    let footer = layout.info[0];
+                    assert_eq!(footer.bottom(), height);
+                    assert!(footer.y >= layout.board.bottom());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bot_plans_only_legal_placements() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        for seed in 1..=5 {
+            let mut tetris = Tetris::new_with_seed(
+                Rect::new(0, 0, 80, 20),
+                Config::default(),
+                KeyBindings::default(),
+                seed,
+            );
+            // ragged garbage stack, one gap in every line and random cells on top of it
+            let mut rng = StdRng::seed_from_u64(seed);
+            let engine = &mut tetris.engine;
+            let (width, height) = (engine.game_width, engine.game_height);
+            for x in width - 6..width {
+                let gap = rng.gen_range(0..height);
+                for y in 0..height {
+                    let filled = y != gap && (x > width - 4 || rng.gen_bool(0.5));
+                    engine.filled_area[x][y] = filled.then_some(GARBAGE);
+                }
+            }
+
+            let plan = tetris.plan_placement().unwrap();
+            let mut pattern = tetris.engine.current_block.pattern.clone();
+            for _ in 0..plan.rotations {
+                pattern = TetrisBlock::rotate90(&pattern);
+            }
+            let engine = &tetris.engine;
+            // the planner tries every side position from where the piece is now
+            let pos = (engine.current_block.pos.0, plan.y);
+            assert!(engine.fits(&pattern, pos), "seed {seed} {plan:?}");
+            // dropped from there it comes to rest on the stack or the floor
+            let mut end = pos;
+            while engine.fits(&pattern, (end.0 + 1, end.1)) {
+                end.0 += 1;
+            }
+            let resting = pattern.iter().enumerate().any(|(i, col)| {
+                col.iter().enumerate().any(|(j, filled)| {
+                    let x = end.0 as usize + i + 1;
+                    let y = (end.1 + j as i32) as usize;
+                    *filled && (x == width || engine.filled_area[x][y] != EMPTY)
+                })
+            });
+            assert!(resting, "seed {seed} {plan:?}");
+        }
+    }
+
+    /// Filled cells of the board as exported, rows from the top of the screen
+    fn screen_cells(tetris: &Tetris) -> Vec<Vec<bool>> {
+        tetris
+            .export_board(false)
+            .lines()
+            .filter(|line| line.starts_with('|'))
+            .map(|line| {
+                line.trim_matches('|')
+                    .as_bytes()
+                    .chunks(2)
+                    .map(|cell| cell == b"[]")
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn height_profile_matches_the_vertical_board_on_screen() {
+        let mut tetris = tetris(Config {
+            orientation: Orientation::Vertical,
+            ..Config::default()
+        });
+        let engine = &mut tetris.engine;
+        let floor = engine.game_width - 1;
+        // bottom row with a gap in the first column, covered by an overhang two rows up
+        for y in 1..engine.game_height {
+            engine.filled_area[floor][y] = Some(GARBAGE);
+        }
+        engine.filled_area[floor - 1][1] = Some(GARBAGE);
+        engine.filled_area[floor - 2][0] = Some(GARBAGE);
+        engine.filled_area[floor - 2][1] = Some(GARBAGE);
+
+        // columns on screen are measured from the bottom row, below the falling piece
+        let rows = screen_cells(&tetris);
+        let stack = &rows[rows.len() - 4..];
+        let heights: Vec<usize> = (0..rows[0].len())
+            .map(|col| {
+                stack
+                    .iter()
+                    .position(|row| row[col])
+                    .map_or(0, |row| stack.len() - row)
+            })
+            .collect();
+        assert_eq!(heights[..3], [3, 3, 1]);
+        assert_eq!(tetris.engine.height_profile(), heights);
+        // both cells under the overhang in the first column, the gap of the bottom row included
+        assert_eq!(tetris.engine.holes(), 2);
+    }
+
+    #[test]
+    fn full_row_clears_on_a_vertical_board() {
+        let mut tetris = tetris(Config {
+            countdown: false,
+            orientation: Orientation::Vertical,
+            ..Config::default()
+        });
+        let engine = &mut tetris.engine;
+        let floor = engine.game_width - 1;
+        // bottom row missing its first cell, with a cell resting on it
+        for y in 1..engine.game_height {
+            engine.filled_area[floor][y] = Some(GARBAGE);
+        }
+        engine.filled_area[floor - 1][1] = Some(GARBAGE);
+        engine.current_block.pattern = vec![vec![true]];
+        engine.current_block.pos = (0, 0);
+
+        let rows = screen_cells(&tetris);
+        assert_eq!(rows.len(), floor + 1);
+        assert!(!rows[floor][0] && rows[floor][1..].iter().all(|cell| *cell));
+
+        // the piece falls down the screen into the gap, completing the bottom row
+        tetris.apply_action(Action::HardDrop);
+        // the next piece stays at the top while the clear plays out
+        tetris.gravity_enabled = false;
+        tetris.update(Duration::from_secs(1));
+        assert_eq!(tetris.engine.lines, 1);
+
+        // the cell above it drops into the bottom row, nothing is left over it
+        let rows = screen_cells(&tetris);
+        let mut bottom = vec![false; rows[floor].len()];
+        bottom[1] = true;
+        assert_eq!(rows[floor], bottom);
+        assert!(rows[floor - 1].iter().all(|cell| !cell));
+    }
+
+    #[test]
+    fn standard_playfield_sits_in_the_middle() {
+        let config = Config {
+            playfield: Playfield::Standard,
+            orientation: Orientation::Vertical,
+            ..Config::default()
+        };
+        let layout = Layout::new(Rect::new(0, 0, 80, 30), &config);
+        assert_eq!(layout.field, Rect::new(0, 0, 80, 25));
+        assert_eq!(layout.board, Rect::new(34, 6, 12, 12));
+        assert_eq!(layout.game_size(config.orientation), (20, 10));
+
+        // the board has to fit, even with the compact HUD
+        assert_eq!(Tetris::min_size(&config), (57, 13));
+    }
+
+    #[test]
+    fn current_outline_stays_on_empty_board_cells() {
+        let mut tetris = tetris(Config::default());
+        let block = &mut tetris.engine.current_block;
+        block.pattern = vec![vec![true, true], vec![true, true]];
+        block.pos = (0, 0);
+        tetris.engine.filled_area[2][1] = Some(GARBAGE);
+
+        // the corner piece has no neighbors past the spawn edge or the side, the stack is skipped
+        let mut cells = tetris.outline_cells();
+        cells.sort();
+        assert_eq!(cells, vec![(0, 2), (1, 2), (2, 0), (2, 2)]);
+    }
+
+    #[test]
+    fn ghost_cells_over_a_gap_are_unsupported() {
+        let mut tetris = tetris(Config::default());
+        let floor = tetris.engine.game_width - 1;
+        tetris.engine.current_block.pattern = vec![vec![true, true], vec![true, true]];
+        tetris.engine.filled_area[floor][0] = Some(GARBAGE);
+
+        // resting on a single cell, the far column has a gap beside it
+        let support = tetris.ghost_support((floor as i32 - 2, 0));
+        assert_eq!(support, [[true, true], [true, false]]);
+        // on the floor every cell is supported
+        let support = tetris.ghost_support((floor as i32 - 1, 2));
+        assert_eq!(support, [[true, true], [true, true]]);
+    }
+
+    #[test]
+    fn outlines_are_drawn_only_between_different_colors() {
+        let mut tetris = tetris(Config::default());
+        let board = &mut tetris.engine.filled_area;
+        // two cells of one piece, a differently colored cell after it on each axis
+        board[10][3] = Some(1);
+        board[10][4] = Some(1);
+        board[10][5] = Some(2);
+        board[11][3] = Some(2);
+        board[11][4] = Some(2);
+
+        assert!(!tetris.is_outline(10, 3));
+        assert!(!tetris.is_outline(10, 4));
+        assert!(tetris.is_outline(10, 5));
+        assert!(tetris.is_outline(11, 3));
+        // borders the first piece along gravity, the cell beside it is the same color
+        assert!(tetris.is_outline(11, 4));
+        // empty cells next to a piece don't make an edge
+        tetris.engine.filled_area[20][0] = Some(3);
+        assert!(!tetris.is_outline(20, 0));
+    }
+
+    #[test]
+    fn limited_colors_mark_pieces_with_their_glyphs() {
+        for color_mode in [ColorMode::Mono, ColorMode::Basic, ColorMode::Full] {
+            let mut tetris = tetris(Config {
+                countdown: false,
+                color_mode,
+                ..Config::default()
+            });
+            // a stack cell of each kind on the floor, the current piece somewhere above them
+            let floor = tetris.engine.game_width - 1;
+            let colors = tetris.theme.pieces.unwrap();
+            for (y, color) in colors.iter().enumerate() {
+                tetris.engine.filled_area[floor][y * 2] = Some(*color);
+            }
+            let buf = draw(&mut tetris);
+
+            let inner = Block::bordered().inner(tetris.board_rect);
+            let symbols: String = inner
+                .positions()
+                .map(|position| buf[position].symbol())
+                .collect();
+            if color_mode == ColorMode::Full {
+                assert!(!symbols.contains(char::is_alphanumeric), "{symbols}");
+                continue;
+            }
+            for kind in PieceKind::ALL {
+                assert!(symbols.contains(glyph(kind)), "{color_mode} {kind}");
+            }
+            // the stack has a single cell of each kind, more of them are the current piece's
+            let current = glyph(tetris.engine.current_block.kind);
+            assert!(
+                symbols.matches(current).count() > 1,
+                "{color_mode} {current}"
+            );
+        }
+    }
+
+    #[test]
+    fn ghost_shows_up_within_the_configured_distance() {
+        let near = tetris(Config {
+            ghost_distance: 3,
+            ..Config::default()
+        });
+        let (x, y) = near.engine.current_block.pos;
+        assert!(near.ghost_visible((x + 3, y)));
+        assert!(!near.ghost_visible((x + 4, y)));
+
+        // 0 shows it however far the piece lands
+        let always = tetris(Config::default());
+        assert!(always.ghost_visible((x + 40, y)));
+    }
+
+    #[test]
+    fn input_echo_shows_the_last_action_until_it_expires() {
+        let mut tetris = tetris(Config {
+            countdown: false,
+            input_echo: true,
+            input_echo_ms: 500,
+            ..Config::default()
+        });
+        let row = |buf: &Buffer, y: u16| -> String {
+            (0..buf.area.width).map(|x| buf[(x, y)].symbol()).collect()
+        };
+        let echo_row = tetris.board_rect.y + 1;
+
+        press(&mut tetris, KeyCode::Up);
+        press(&mut tetris, KeyCode::Left);
+        assert_eq!(tetris.input_echo.map(|(action, _)| action), Some("Rotate"));
+        let buf = draw(&mut tetris);
+        assert!(row(&buf, echo_row).contains(" Rotate "));
+
+        let long_ago = Instant::now().checked_sub(Duration::from_secs(1)).unwrap();
+        tetris.input_echo = Some(("Rotate", long_ago));
+        let buf = draw(&mut tetris);
+        assert_eq!(tetris.input_echo, None);
+        assert!(!row(&buf, echo_row).contains("Rotate"));
+    }
+
+    #[test]
+    fn spawn_guide_marks_where_the_next_piece_comes_in() {
+        let off = tetris(Config::default());
+        assert!(off.spawn_guide_cells().is_empty());
+
+        let mut tetris = tetris(Config {
+            spawn_guide: true,
+            ..Config::default()
+        });
+        // the next piece spawns where the current one is along the side axis
+        tetris.engine.current_block.pos.1 = 3;
+        let cells = tetris.spawn_guide_cells();
+        let next = &tetris.engine.next_block().pattern;
+        let y = tetris.engine.next_spawn_y();
+        assert_eq!(y, 3);
+        // the block the piece spans from the spawn edge
+        let expected: Vec<_> = (0..next.len())
+            .flat_map(|i| (y..y + next[0].len()).map(move |j| (i, j)))
+            .collect();
+        assert_eq!(cells, expected);
+    }
+
+    #[test]
+    fn short_screens_get_the_compact_hud() {
+        let config = Config::default();
+        let layout = Layout::new(Rect::new(0, 0, 80, 12), &config);
+        assert!(layout.compact);
+        // the board takes all but the status line
+        assert_eq!(layout.board.height, 11);
+        assert_eq!(layout.info, [Rect::new(0, 11, 80, 1)]);
+        assert!(layout.next.is_empty());
+        assert_eq!(Tetris::screen_height(&config, 8), 11);
+
+        let layout = Layout::new(Rect::new(0, 0, 80, 20), &config);
+        assert!(!layout.compact);
+        assert_eq!(Tetris::screen_height(&config, 13), 20);
+
+        // screens under the absolute minimum keep its size, showing the too small message
+        let layout = Layout::new(Rect::new(0, 0, 80, 4), &config);
+        assert_eq!(layout.screen.height, 6);
+    }
+
+    #[test]
+    fn rotation_during_the_countdown_is_applied_once_play_begins() {
+        let mut tetris = tetris(Config::default());
+        let orientation = tetris.engine.current_block.orientation;
+        press(&mut tetris, KeyCode::Left);
+
+        tetris.update(Duration::from_secs(1));
+        assert_eq!(tetris.engine.game_state, GameState::Starting);
+        assert_eq!(tetris.engine.current_block.orientation, orientation);
+
+        tetris.update(Duration::from_secs(3));
+        assert_eq!(tetris.engine.game_state, GameState::Playing);
+        assert_eq!(
+            tetris.engine.current_block.orientation,
+            (orientation + 1) % 4
+        );
+
+        tetris.update(Duration::from_millis(10));
+        assert_eq!(
+            tetris.engine.current_block.orientation,
+            (orientation + 1) % 4
+        );
+    }
+
+    #[test]
+    fn paused_time_moves_neither_gravity_nor_the_lock_delay() {
+        let config = Config {
+            countdown: false,
+            ..Config::default()
+        };
+        let mut tetris = tetris(config);
+        let interval = tetris.engine.move_interval;
+        let x = tetris.engine.current_block.pos.0;
+
+        tetris.update(interval * 6 / 10);
+        press(&mut tetris, KeyCode::Char('p'));
+        tetris.update(interval * 10);
+        assert_eq!(tetris.engine.current_block.pos.0, x);
+
+        // resuming keeps what was left of the interval
+        press(&mut tetris, KeyCode::Char('p'));
+        assert_eq!(tetris.engine.game_state, GameState::Playing);
+        tetris.update(interval * 3 / 10);
+        assert_eq!(tetris.engine.current_block.pos.0, x);
+        tetris.update(interval * 2 / 10);
+        assert_eq!(tetris.engine.current_block.pos.0, x + 1);
+
+        // a piece resting on the floor doesn't lock while paused
+        let end = tetris.engine.get_end_move_pos().0;
+        tetris.engine.current_block.pos.0 = end;
+        tetris.engine.move_forward();
+        press(&mut tetris, KeyCode::Char('p'));
+        tetris.update(Duration::from_secs(5));
+        press(&mut tetris, KeyCode::Char('p'));
+        tetris.update(Duration::from_millis(1));
+        assert_eq!(tetris.engine.rounds, 0);
+        tetris.update(Duration::from_millis(tetris.config.lock_delay_ms));
+        assert_eq!(tetris.engine.rounds, 1);
+    }
+
+    #[test]
+    fn empty_input_buffer_drops_moves_during_the_countdown() {
+        let config = Config {
+            input_buffer: 0,
+            ..Config::default()
+        };
+        let mut tetris = tetris(config);
+        let orientation = tetris.engine.current_block.orientation;
+        press(&mut tetris, KeyCode::Left);
+
+        tetris.update(Duration::from_secs(4));
+        assert_eq!(tetris.engine.game_state, GameState::Playing);
+        assert_eq!(tetris.engine.current_block.orientation, orientation);
+    }
+
+    #[test]
+    fn double_tap_moves_the_piece_to_the_wall() {
+        for double_tap in [false, true] {
+            let config = Config {
+                countdown: false,
+                double_tap,
+                ..Config::default()
+            };
+            let mut tetris = tetris(config);
+            let (x, y) = tetris.engine.current_block.pos;
+            press(&mut tetris, KeyCode::Down);
+            press(&mut tetris, KeyCode::Down);
+
+            let block = &tetris.engine.current_block;
+            let at_wall = !tetris.engine.fits(&block.pattern, (x, block.pos.1 - 1));
+            assert_eq!(at_wall, double_tap);
+            if !double_tap {
+                assert_eq!(block.pos, (x, y - 2));
+            }
+        }
+    }
+
+    #[test]
+    fn debounced_input_drops_repeats_within_the_window() {
+        let config = Config {
+            countdown: false,
+            input_mode: InputMode::Debounced,
+            debounce_ms: 1000,
+            ..Config::default()
+        };
+        let mut tetris = tetris(config);
+        let (_, y) = tetris.engine.current_block.pos;
+        press(&mut tetris, KeyCode::Up);
+        press(&mut tetris, KeyCode::Up);
+        assert_eq!(tetris.engine.current_block.pos.1, y + 1);
+
+        // a different key isn't a repeat
+        press(&mut tetris, KeyCode::Down);
+        assert_eq!(tetris.engine.current_block.pos.1, y);
+
+        // the same key once the window passed counts again
+        let long_ago = Instant::now().checked_sub(Duration::from_secs(2)).unwrap();
+        tetris.last_key = Some((KeyCode::Down, long_ago));
+        press(&mut tetris, KeyCode::Down);
+        assert_eq!(tetris.engine.current_block.pos.1, y - 1);
+    }
+
+    #[test]
+    fn gravity_keeps_its_cadence_with_uneven_updates() {
+        let config = Config {
+            countdown: false,
+            ..Config::default()
+        };
+        let mut tetris = tetris(config);
+        let interval = tetris.engine.move_interval;
+        let x = tetris.engine.current_block.pos.0;
+
+        // updates 0.7 of an interval apart, 7 intervals in total
+        for _ in 0..10 {
+            tetris.update(interval * 7 / 10);
+        }
+        assert_eq!(tetris.engine.current_block.pos.0, x + 7);
+    }
+
+    #[test]
+    fn actions_drive_the_game_without_keys() {
+        let config = Config {
+            countdown: false,
+            entry_delay_ms: 0,
+            ..Config::default()
+        };
+        let mut tetris = tetris(config);
+        let (x, y) = tetris.engine.current_block.pos;
+        tetris.apply_action(Action::MoveUp);
+        tetris.apply_action(Action::MoveUp);
+        tetris.apply_action(Action::SoftDrop);
+        assert_eq!(tetris.engine.current_block.pos, (x + 1, y + 2));
+
+        tetris.apply_action(Action::HardDrop);
+        assert_eq!(tetris.engine.rounds, 1);
+        tetris.apply_action(Action::Pause);
+        assert_eq!(tetris.engine.game_state, GameState::Paused);
+        tetris.apply_action(Action::Quit);
+        assert!(tetris.pending_quit);
+        press(&mut tetris, KeyCode::Char('y'));
+        assert!(tetris.exit);
+    }
+
+    #[test]
+    fn stack_near_the_spawn_edge_is_a_danger() {
+        let config = Config {
+            countdown: false,
+            ..Config::default()
+        };
+        let mut tetris = tetris(config);
+        assert!(!tetris.in_danger());
+
+        let engine = &mut tetris.engine;
+        let y = engine.next_spawn_y();
+        let width = engine.game_width;
+        // stack reaching 5 cells from the spawn edge is still fine, 4 is too close
+        for x in 5..width {
+            engine.filled_area[x][y] = Some(GARBAGE);
+        }
+        assert!(!tetris.in_danger());
+        tetris.engine.filled_area[4][y] = Some(GARBAGE);
+        assert!(tetris.in_danger());
+
+        tetris.config.danger_distance = 0;
+        assert!(!tetris.in_danger());
+    }
+
+    #[test]
+    fn piece_stays_put_with_gravity_off() {
+        let config = Config {
+            countdown: false,
+            ..Config::default()
+        };
+        let mut tetris = tetris(config);
+        press(&mut tetris, KeyCode::Char('f'));
+        let pos = tetris.engine.current_block.pos;
+
+        for _ in 0..10 {
+            tetris.update(tetris.gravity_interval());
+        }
+        assert_eq!(tetris.engine.current_block.pos, pos);
+
+        // input still moves it
+        press(&mut tetris, KeyCode::Right);
+        assert_eq!(tetris.engine.current_block.pos.0, pos.0 + 1);
+
+        press(&mut tetris, KeyCode::Char('f'));
+        tetris.update(tetris.gravity_interval());
+        assert_eq!(tetris.engine.current_block.pos.0, pos.0 + 2);
+    }
+
+    #[test]
+    fn practice_mode_clears_the_board_and_keeps_playing() {
+        let config = Config {
+            countdown: false,
+            ..Config::default()
+        };
+        let mut normal = tetris(config.clone());
+        normal.apply_action(Action::HardDrop);
+        press(&mut normal, KeyCode::Char('x'));
+        assert!(normal
+            .engine
+            .filled_area
+            .iter()
+            .flatten()
+            .any(Option::is_some));
+
+        let mut practice = tetris(Config {
+            practice: true,
+            ..config
+        });
+        practice.apply_action(Action::HardDrop);
+        practice.update(Duration::from_secs(1));
+        practice.apply_action(Action::SoftDrop);
+        let points = practice.engine.points;
+        press(&mut practice, KeyCode::Char('x'));
+        assert!(practice
+            .engine
+            .filled_area
+            .iter()
+            .flatten()
+            .all(Option::is_none));
+        assert_eq!(practice.engine.game_state, GameState::Playing);
+        assert_eq!(practice.engine.points, points);
+        assert_eq!(practice.engine.current_block.pos.0, 0);
+    }
+
+    #[test]
+    fn watching_a_replay_plays_the_same_game() {
+        let path = std::env::temp_dir().join(format!("tetris-watch-{}.txt", std::process::id()));
+        let config = Config {
+            countdown: false,
+            ..Config::default()
+        };
+        let mut player = tetris(config.clone());
+        player.record(Recorder::create(&path).unwrap()).unwrap();
+        let mut follower = Follower::open(&path).unwrap();
+
+        let mut watcher = tetris(config);
+        for key in [KeyCode::Left, KeyCode::Up, KeyCode::Char(' ')] {
+            press(&mut player, key);
+        }
+        player.update(player.gravity_interval());
+        press(&mut player, KeyCode::Char(' '));
+
+        let header = follower.header().unwrap();
+        assert_eq!(header.seed, player.engine.seed);
+        watcher.watch(follower);
+        watcher.update(Duration::ZERO);
+        assert_eq!(watcher.engine.rounds, 2);
+        assert_eq!(watcher.engine.filled_area, player.engine.filled_area);
+
+        // the rest is played as it's written
+        press(&mut player, KeyCode::Char(' '));
+        player
+            .recorder
+            .take()
+            .unwrap()
+            .finish(Duration::ZERO)
+            .unwrap();
+        watcher.update(Duration::ZERO);
+        assert_eq!(watcher.engine.filled_area, player.engine.filled_area);
+        assert!(watcher.follower.as_ref().unwrap().ended());
+
+        // keys don't play the watched game
+        press(&mut watcher, KeyCode::Char(' '));
+        assert_eq!(watcher.engine.rounds, 3);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn quitting_a_game_asks_first() {
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        let config = Config {
+            countdown: false,
+            ..Config::default()
+        };
+        let mut instant = tetris(Config {
+            confirm_quit: false,
+            ..config.clone()
+        });
+        instant.handle_key(ctrl_c).unwrap();
+        assert!(instant.exit);
+
+        let mut tetris = tetris(config);
+        tetris.handle_key(ctrl_c).unwrap();
+        assert!(tetris.pending_quit && !tetris.exit);
+        // the game waits for the answer
+        let pos = tetris.engine.current_block.pos;
+        tetris.update(Duration::from_secs(1));
+        assert_eq!(tetris.engine.current_block.pos, pos);
+
+        press(&mut tetris, KeyCode::Char('n'));
+        assert!(!tetris.pending_quit && !tetris.exit);
+        tetris.handle_key(ctrl_c).unwrap();
+        press(&mut tetris, KeyCode::Char('y'));
+        assert!(tetris.exit);
+    }
+
+    #[test]
+    fn move_during_the_entry_delay_brings_the_next_piece_in() {
+        let config = Config {
+            countdown: false,
+            ..Config::default()
+        };
+        let mut tetris = tetris(config);
+        let next = tetris.engine.next_block().kind;
+        press(&mut tetris, KeyCode::Char(' '));
+        assert!(tetris.engine.is_entering());
+
+        let y = tetris.engine.next_spawn_y() as i32;
+        press(&mut tetris, KeyCode::Down);
+        assert!(!tetris.engine.is_entering());
+        assert_eq!(tetris.engine.current_block.kind, next);
+        assert_eq!(tetris.engine.current_block.pos, (0, y - 1));
+    }
+
+    #[test]
+    fn exported_board_shows_the_stack_and_the_piece() {
+        let mut tetris = tetris(Config::default());
+        let floor = tetris.engine.game_width - 1;
+        tetris.engine.filled_area[floor][0] = Some(9);
+
+        let plain = tetris.export_board(false);
+        let lines: Vec<&str> = plain.lines().collect();
+        assert_eq!(lines.len(), tetris.engine.game_height + 2);
+        assert_eq!(plain.matches("[]").count(), 5);
+        // horizontal board has the floor on the right and y growing upwards
+        assert!(lines[lines.len() - 2].ends_with("[]|"));
+
+        let colored = tetris.export_board(true);
+        assert!(colored.contains("\x1b[38;5;9m██\x1b[0m"));
+    }
+
+    #[test]
+    fn idle_game_over_screen_starts_the_demo_until_a_key() {
+        let config = Config {
+            idle_demo_ms: 1000,
+            ..Config::default()
+        };
+        let mut tetris = tetris(config);
+        tetris.engine.end_game(false);
+        tetris.update(Duration::from_millis(10));
+        assert!(!tetris.attract);
+
+        tetris.last_input -= Duration::from_secs(2);
+        tetris.update(Duration::from_millis(10));
+        assert!(tetris.attract);
+        assert_eq!(tetris.engine.game_state, GameState::Playing);
+        for _ in 0..50 {
+            tetris.update(Duration::from_millis(100));
+        }
+        assert!(tetris.engine.rounds > 0);
+
+        press(&mut tetris, KeyCode::Char('x'));
+        assert!(!tetris.attract);
+        assert_eq!(tetris.engine.rounds, 0);
+        assert_eq!(tetris.engine.game_state, GameState::Starting);
+    }
+
+    #[test]
+    fn numbers_too_long_for_the_panel_are_abbreviated() {
+        let tetris = tetris(Config::default());
+        assert_eq!(tetris.format_number(999_999), "999999");
+        assert_eq!(tetris.format_number(1_234_567), "  1.2M");
+        assert_eq!(tetris.format_number(987_654_321), "  987M");
+        assert_eq!(tetris.format_number(u64::MAX), " 18.4E");
+    }
+
+    #[test]
+    fn board_file_stack_starts_every_game() {
+        let mut tetris = tetris(Config::default());
+        let across = tetris.engine.game_height;
+        let text = format!(
+            "# well on the right\n{}..\nttt{}.\n",
+            "X".repeat(across - 2),
+            "O".repeat(across - 4)
+        );
+        let lines = board_file::parse(&text, &tetris.theme).unwrap();
+        assert!(tetris
+            .set_board(vec![vec![Some(GARBAGE); across + 2]])
+            .is_err());
+        tetris.set_board(lines.clone()).unwrap();
+
+        let floor = tetris.engine.game_width - 2;
+        assert_eq!(tetris.engine.filled_area[floor..], lines);
+        assert_eq!(lines[1][..4], [Some(13), Some(13), Some(13), EMPTY]);
+        tetris.restart();
+        assert_eq!(tetris.engine.filled_area[floor..], lines);
+    }
+}
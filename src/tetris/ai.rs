@@ -0,0 +1,127 @@
+use super::{key_bindings::Action, Tetris};
+use tetris_engine::{
+    engine::{height_profile, holes, GameState, EMPTY},
+    tetris_block::TetrisBlock,
+};
+
+/// Where the bot wants to put the current piece
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    /// Round the plan was made for, new piece needs a new plan
+    pub round: u64,
+    pub rotations: usize,
+    pub y: i32,
+}
+
+/// How many actions bot makes per gravity step
+const ACTIONS_PER_STEP: usize = 3;
+
+impl Tetris {
+    /// Tries every rotation and side position of the current piece,
+    /// scoring the board after it lands by lines, stack height, holes and bumpiness
+    pub fn plan_placement(&self) -> Option<Plan> {
+        let engine = &self.engine;
+        let mut pattern = engine.current_block.pattern.clone();
+        let x = engine.current_block.pos.0;
+        let mut best: Option<(f64, Plan)> = None;
+
+        for rotations in 0..4 {
+            for y in 0..engine.game_height as i32 {
+                if !engine.fits(&pattern, (x, y)) {
+                    continue;
+                }
+                let mut end_x = x;
+                while engine.fits(&pattern, (end_x + 1, y)) {
+                    end_x += 1;
+                }
+                let score = self.placement_score(&pattern, (end_x, y));
+                if best
+                    .as_ref()
+                    .is_none_or(|(best_score, _)| score > *best_score)
+                {
+                    best = Some((
+                        score,
+                        Plan {
+                            round: engine.rounds,
+                            rotations,
+                            y,
+                        },
+                    ));
+                }
+            }
+            pattern = TetrisBlock::rotate90(&pattern);
+        }
+
+        best.map(|(_, plan)| plan)
+    }
+
+    fn placement_score(&self, pattern: &[Vec<bool>], pos: (i32, i32)) -> f64 {
+        let mut board = self.engine.filled_area.clone();
+        for (i, col) in pattern.iter().enumerate() {
+            for (j, draw) in col.iter().enumerate() {
+                if let (true, Some(y)) = (*draw, self.engine.cell_y(pos.1 + j as i32)) {
+                    board[pos.0 as usize + i][y] = Some(self.engine.current_block.color);
+                }
+            }
+        }
+
+        let lines = board
+            .iter()
+            .filter(|col| col.iter().all(|c| *c != EMPTY))
+            .count();
+        let profile = height_profile(&board);
+        let height: usize = profile.iter().sum();
+        let bumpiness: usize = profile.windows(2).map(|w| w[0].abs_diff(w[1])).sum();
+
+        0.76 * lines as f64
+            - 0.51 * height as f64
+            - 0.36 * holes(&board) as f64
+            - 0.18 * bumpiness as f64
+    }
+
+    /// Makes a few moves towards the planned placement, drops the piece once it's there
+    pub(super) fn bot_step(&mut self) {
+        match self.engine.game_state {
+            GameState::Finished => return self.restart(),
+            GameState::Paused | GameState::Starting => return,
+            GameState::Playing => {}
+        }
+
+        if self
+            .bot_plan
+            .as_ref()
+            .is_none_or(|plan| plan.round != self.engine.rounds)
+        {
+            self.bot_plan = self.plan_placement();
+        }
+
+        for _ in 0..ACTIONS_PER_STEP {
+            let Some(plan) = self.bot_plan.as_mut() else {
+                return;
+            };
+            if plan.rotations > 0 {
+                plan.rotations -= 1;
+                self.apply_action(Action::Rotate);
+                continue;
+            }
+
+            let y = self.engine.current_block.pos.1;
+            let target = plan.y;
+            if y == target {
+                self.apply_action(Action::HardDrop);
+                return;
+            }
+
+            self.apply_action(if target > y {
+                Action::MoveUp
+            } else {
+                Action::MoveDown
+            });
+            if self.engine.current_block.pos.1 == y {
+                // blocked, drop where it is
+                self.apply_action(Action::HardDrop);
+                return;
+            }
+        }
+    }
+}
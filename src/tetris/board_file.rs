@@ -0,0 +1,61 @@
+use super::theme::{Theme, ThemePreset};
+use std::{fs, path::Path};
+use tetris_engine::{
+    engine::{EMPTY, GARBAGE},
+    tetris_block::PieceKind,
+};
+
+/// Reads the stack a game starts with, see `parse`
+pub fn load(path: &Path, theme: &Theme) -> Result<Vec<Vec<Option<u8>>>, String> {
+    let text = fs::read_to_string(path).map_err(|error| format!("{}: {error}", path.display()))?;
+    parse(&text, theme).map_err(|error| format!("{}: {error}", path.display()))
+}
+
+/// Parses board lines from the one closest to the spawn edge to the floor, each drawn across
+/// the board like on a vertical one. `X` is a garbage cell, `O` and `.` are empty and
+/// lowercase piece letters (`i`, `o`, `t`, `s`, `z`, `j`, `l`) are cells in that piece's color
+pub fn parse(text: &str, theme: &Theme) -> Result<Vec<Vec<Option<u8>>>, String> {
+    let colors = theme
+        .pieces
+        .or(Theme::preset(ThemePreset::Classic).pieces)
+        .unwrap_or_default();
+    let mut lines: Vec<Vec<Option<u8>>> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let cells = line
+            .chars()
+            .map(|c| match c {
+                'X' => Ok(Some(GARBAGE)),
+                'O' | '.' => Ok(EMPTY),
+                _ => PieceKind::ALL
+                    .into_iter()
+                    .find(|kind| kind.to_string().to_lowercase() == c.to_string())
+                    .map(|kind| Some(colors[kind.index()]))
+                    .ok_or_else(|| {
+                        format!(
+                            "line {}: unknown cell `{c}`, expected X, O, . or a lowercase piece letter",
+                            number + 1
+                        )
+                    }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(first) = lines.first() {
+            if cells.len() != first.len() {
+                return Err(format!(
+                    "line {}: {} cells across, earlier lines have {}",
+                    number + 1,
+                    cells.len(),
+                    first.len()
+                ));
+            }
+        }
+        lines.push(cells);
+    }
+    if lines.is_empty() {
+        return Err("no board lines".to_string());
+    }
+    Ok(lines)
+}
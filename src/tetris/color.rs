@@ -0,0 +1,308 @@
+use ratatui::style::Color;
+use std::{env, fmt, str::FromStr};
+use tetris_engine::tetris_block::PieceKind;
+
+/// How many colors terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Detect from environment
+    Auto,
+    /// 256 colors or more
+    Full,
+    /// Basic 16 colors
+    Basic,
+    /// No colors
+    Mono,
+}
+
+impl ColorMode {
+    /// Resolves `Auto` based on `NO_COLOR`, `COLORTERM` and `TERM` variables
+    pub fn resolve(self) -> Self {
+        match self {
+            ColorMode::Auto => Self::detect(
+                env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+                env::var("COLORTERM").ok().as_deref(),
+                env::var("TERM").ok().as_deref(),
+            ),
+            mode => mode,
+        }
+    }
+
+    pub fn detect(no_color: bool, colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if no_color {
+            return ColorMode::Mono;
+        }
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            return ColorMode::Full;
+        }
+        match term {
+            None | Some("" | "dumb") => ColorMode::Mono,
+            Some(term) if term.contains("256color") || term.contains("direct") => ColorMode::Full,
+            Some(_) => ColorMode::Basic,
+        }
+    }
+
+    /// Whether pieces are marked with glyphs, with fewer than 256 colors some of them look the same
+    pub fn glyphs(self) -> bool {
+        matches!(self, ColorMode::Basic | ColorMode::Mono)
+    }
+
+    /// Maps color to closest one terminal can show
+    pub fn map(self, color: Color) -> Color {
+        match self {
+            ColorMode::Auto | ColorMode::Full => color,
+            ColorMode::Basic => to_basic(color),
+            ColorMode::Mono => match color {
+                Color::Black | Color::Reset => color,
+                _ => Color::White,
+            },
+        }
+    }
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorMode::Auto),
+            "256" => Ok(ColorMode::Full),
+            "16" => Ok(ColorMode::Basic),
+            "mono" => Ok(ColorMode::Mono),
+            _ => Err(format!("unknown color mode {s}")),
+        }
+    }
+}
+
+impl fmt::Display for ColorMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ColorMode::Auto => "auto",
+            ColorMode::Full => "256",
+            ColorMode::Basic => "16",
+            ColorMode::Mono => "mono",
+        })
+    }
+}
+
+/// Character a piece's cells are drawn with when colors can't tell the pieces apart,
+/// the letter of the kind, custom pieces are numbered from 1 and then lettered from a
+pub fn glyph(kind: PieceKind) -> char {
+    match kind {
+        PieceKind::Custom(index) => char::from_digit(index as u32 + 1, 36).unwrap_or('*'),
+        kind => kind.to_string().chars().next().unwrap_or('*'),
+    }
+}
+
+const BASIC: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+fn to_basic(color: Color) -> Color {
+    match color {
+        Color::Indexed(i @ 0..=15) => BASIC[i as usize],
+        Color::Indexed(i @ 16..=231) => {
+            let i = i - 16;
+            from_rgb(i / 36 * 51, i / 6 % 6 * 51, i % 6 * 51)
+        }
+        Color::Indexed(i) => match 8 + (i - 232) * 10 {
+            0..=63 => Color::Black,
+            64..=127 => Color::DarkGray,
+            128..=207 => Color::Gray,
+            _ => Color::White,
+        },
+        Color::Rgb(r, g, b) => from_rgb(r, g, b),
+        color => color,
+    }
+}
+
+/// Picks basic color by which channels are at least half as bright as the brightest one
+fn from_rgb(r: u8, g: u8, b: u8) -> Color {
+    let max = r.max(g).max(b) as u16;
+    if max < 64 {
+        return Color::Black;
+    }
+    let on = |c: u8| usize::from(c as u16 * 2 > max);
+    let index = on(r) + on(g) * 2 + on(b) * 4;
+    BASIC[index + if max > 191 { 8 } else { 0 }]
+}
+
+pub fn darken(color: Color) -> Color {
+    match color {
+        Color::Indexed(i @ 9..=15) => Color::Indexed(i - 8),
+        Color::Indexed(7) => Color::Indexed(8),
+        Color::LightRed => Color::Red,
+        Color::LightGreen => Color::Green,
+        Color::LightYellow => Color::Yellow,
+        Color::LightBlue => Color::Blue,
+        Color::LightMagenta => Color::Magenta,
+        Color::LightCyan => Color::Cyan,
+        Color::White => Color::Gray,
+        Color::Gray => Color::DarkGray,
+        other => other,
+    }
+}
+
+/// Color `amount` (0 to 1) of the way from `from` to `to`, as the closest entry of the 256 color palette
+pub fn blend(from: Color, to: Color, amount: f64) -> Color {
+    if amount <= 0.0 {
+        return from;
+    }
+    if amount >= 1.0 {
+        return to;
+    }
+    let (from, to) = (to_rgb(from), to_rgb(to));
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * amount).round() as u8;
+    to_indexed(mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+}
+
+/// Channels of the first 16 palette colors as xterm shows them
+const SYSTEM_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// Channel values of the 6x6x6 color cube
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Channels of the color, the terminal's default color counts as black
+fn to_rgb(color: Color) -> (u8, u8, u8) {
+    match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(i @ 0..=15) => SYSTEM_RGB[i as usize],
+        Color::Indexed(i @ 16..=231) => {
+            let i = (i - 16) as usize;
+            (
+                CUBE_LEVELS[i / 36],
+                CUBE_LEVELS[i / 6 % 6],
+                CUBE_LEVELS[i % 6],
+            )
+        }
+        Color::Indexed(i) => {
+            let gray = 8 + (i - 232) * 10;
+            (gray, gray, gray)
+        }
+        color => BASIC
+            .iter()
+            .position(|basic| *basic == color)
+            .map_or((0, 0, 0), |i| SYSTEM_RGB[i]),
+    }
+}
+
+/// Closest color of the cube or the gray ramp, which look the same in every 256 color terminal
+fn to_indexed(r: u8, g: u8, b: u8) -> Color {
+    let level = |c: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|i| CUBE_LEVELS[*i].abs_diff(c))
+            .unwrap()
+    };
+    let cube = (level(r), level(g), level(b));
+    let cube_rgb = (
+        CUBE_LEVELS[cube.0],
+        CUBE_LEVELS[cube.1],
+        CUBE_LEVELS[cube.2],
+    );
+    let step = ((r as u16 + g as u16 + b as u16) / 3).saturating_sub(3) / 10;
+    let step = step.min(23) as u8;
+    let gray = 8 + step * 10;
+
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        [(r, r2), (g, g2), (b, b2)]
+            .iter()
+            .map(|(a, b)| (a.abs_diff(*b) as u32).pow(2))
+            .sum::<u32>()
+    };
+    if distance((gray, gray, gray)) < distance(cube_rgb) {
+        Color::Indexed(232 + step)
+    } else {
+        Color::Indexed(16 + 36 * cube.0 as u8 + 6 * cube.1 as u8 + cube.2 as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn detects_colors_from_the_environment() {
+        let detect = ColorMode::detect;
+        assert_eq!(
+            detect(true, Some("truecolor"), Some("xterm-256color")),
+            ColorMode::Mono
+        );
+        assert_eq!(
+            detect(false, Some("truecolor"), Some("xterm")),
+            ColorMode::Full
+        );
+        assert_eq!(detect(false, None, Some("xterm-256color")), ColorMode::Full);
+        assert_eq!(detect(false, None, Some("xterm")), ColorMode::Basic);
+        assert_eq!(detect(false, None, Some("linux")), ColorMode::Basic);
+        assert_eq!(detect(false, None, Some("dumb")), ColorMode::Mono);
+        assert_eq!(detect(false, None, None), ColorMode::Mono);
+    }
+
+    #[test]
+    fn limited_colors_fall_back_to_glyphs() {
+        let detect = |term| ColorMode::detect(false, None, Some(term));
+        assert!(detect("xterm").glyphs());
+        assert!(detect("dumb").glyphs());
+        assert!(!detect("xterm-256color").glyphs());
+
+        let glyphs: HashSet<char> = PieceKind::ALL.into_iter().map(glyph).collect();
+        assert_eq!(glyphs.len(), PieceKind::ALL.len());
+        assert_eq!(glyph(PieceKind::T), 'T');
+        assert_eq!(glyph(PieceKind::Custom(0)), '1');
+        assert_eq!(glyph(PieceKind::Custom(9)), 'a');
+    }
+
+    #[test]
+    fn limited_terminals_get_the_closest_color() {
+        for (index, basic) in [
+            (9, Color::LightRed),
+            (196, Color::LightRed),
+            (21, Color::LightBlue),
+            (28, Color::Green),
+            (244, Color::Gray),
+            (232, Color::Black),
+        ] {
+            assert_eq!(
+                ColorMode::Basic.map(Color::Indexed(index)),
+                basic,
+                "{index}"
+            );
+        }
+        assert_eq!(ColorMode::Mono.map(Color::Indexed(9)), Color::White);
+        assert_eq!(ColorMode::Mono.map(Color::Black), Color::Black);
+        assert_eq!(ColorMode::Full.map(Color::Indexed(9)), Color::Indexed(9));
+    }
+}
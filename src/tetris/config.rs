@@ -0,0 +1,949 @@
+use super::{
+    color::ColorMode,
+    theme::{Theme, ThemePreset},
+};
+use ratatui::style::Color;
+use std::{
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+use tetris_engine::{
+    engine::{LockReset, Rules},
+    game_mode::GameMode,
+    piece_bag::Randomizer,
+    tetris_block::PieceKind,
+};
+
+/// How bursts of key events are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputMode {
+    /// Every key event is a separate action
+    Precise,
+    /// Repeated key within `debounce_ms` is ignored
+    Debounced,
+}
+
+impl FromStr for InputMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "precise" => Ok(InputMode::Precise),
+            "debounced" => Ok(InputMode::Debounced),
+            _ => Err(format!("unknown input mode {s}")),
+        }
+    }
+}
+
+impl fmt::Display for InputMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InputMode::Precise => "precise",
+            InputMode::Debounced => "debounced",
+        })
+    }
+}
+
+/// Direction pieces fall in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    /// Pieces fall to the right, lines are columns
+    Horizontal,
+    /// Pieces fall down, lines are rows
+    Vertical,
+}
+
+impl FromStr for Orientation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "horizontal" => Ok(Orientation::Horizontal),
+            "vertical" => Ok(Orientation::Vertical),
+            _ => Err(format!("unknown orientation {s}")),
+        }
+    }
+}
+
+impl fmt::Display for Orientation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Orientation::Horizontal => "horizontal",
+            Orientation::Vertical => "vertical",
+        })
+    }
+}
+
+/// Where upcoming pieces are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NextPanel {
+    /// Side by side in the panel under the board
+    Strip,
+    /// One under another in a panel beside the board
+    Column,
+}
+
+impl FromStr for NextPanel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strip" => Ok(NextPanel::Strip),
+            "column" => Ok(NextPanel::Column),
+            _ => Err(format!("unknown next panel {s}")),
+        }
+    }
+}
+
+impl fmt::Display for NextPanel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NextPanel::Strip => "strip",
+            NextPanel::Column => "column",
+        })
+    }
+}
+
+/// How the hold panel shows that hold was already used for the current piece
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoldIndicator {
+    /// Held piece is drawn in the shadow color
+    Dim,
+    /// Held piece keeps its color, a cross in the panel's corner marks the hold as used
+    Glyph,
+    /// Nothing shows it
+    Hidden,
+}
+
+impl FromStr for HoldIndicator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dim" => Ok(HoldIndicator::Dim),
+            "glyph" => Ok(HoldIndicator::Glyph),
+            "hidden" => Ok(HoldIndicator::Hidden),
+            _ => Err(format!("unknown hold indicator {s}")),
+        }
+    }
+}
+
+impl fmt::Display for HoldIndicator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HoldIndicator::Dim => "dim",
+            HoldIndicator::Glyph => "glyph",
+            HoldIndicator::Hidden => "hidden",
+        })
+    }
+}
+
+/// How big the board is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Playfield {
+    /// As big as the terminal, growing with it
+    Fit,
+    /// 10 cells across and 20 along gravity, in the middle of the terminal
+    Standard,
+}
+
+impl Playfield {
+    /// Board size in cells as `--width` and `--height` take it, `None` fits the terminal
+    pub fn size(self, orientation: Orientation) -> Option<(u16, u16)> {
+        match (self, orientation) {
+            (Playfield::Fit, _) => None,
+            (Playfield::Standard, Orientation::Horizontal) => Some((20, 10)),
+            (Playfield::Standard, Orientation::Vertical) => Some((10, 20)),
+        }
+    }
+}
+
+impl FromStr for Playfield {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fit" => Ok(Playfield::Fit),
+            "standard" => Ok(Playfield::Standard),
+            _ => Err(format!("unknown playfield {s}")),
+        }
+    }
+}
+
+impl fmt::Display for Playfield {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Playfield::Fit => "fit",
+            Playfield::Standard => "standard",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    pub shown_help_before: bool,
+    /// `bag` deals every shape once before repeating, `classic` picks each piece independently
+    pub randomizer: Randomizer,
+    /// Pieces spawn randomly rotated
+    pub random_rotation: bool,
+    /// Cells the first piece spawns away from the middle of the board, positive is up
+    pub spawn_offset: i32,
+    /// Tints ghost cells that would leave a gap beneath them
+    pub ghost_support_tint: bool,
+    /// Ghost is shown only within this many cells from landing, 0 shows it always
+    pub ghost_distance: usize,
+    /// Ghost in the piece's color fading into the background the farther it lands
+    pub ghost_gradient: bool,
+    /// Marks the area where the next piece will appear
+    pub spawn_guide: bool,
+    /// Board border flashes red when the stack where pieces spawn gets within this many cells
+    /// of the spawn edge, 0 turns the warning off
+    pub danger_distance: usize,
+    /// Level the game starts at, higher ones fall faster
+    pub start_level: u64,
+    /// How many upcoming pieces are shown in the next panel
+    pub preview_count: usize,
+    /// `strip` shows upcoming pieces under the board, `column` beside it
+    pub next_panel: NextPanel,
+    /// How long a piece can rest on the stack before it locks, in milliseconds, 0 locks at once
+    pub lock_delay_ms: u64,
+    /// `classic` never restarts the lock delay, `step` restarts it on every move or rotation,
+    /// `move` only `max_lock_resets` times per piece
+    pub lock_reset: LockReset,
+    /// How many times per piece moving or rotating restarts the lock delay
+    pub max_lock_resets: u32,
+    /// How many locked pieces undo and the pause menu timeline can go back
+    pub history_length: usize,
+    /// Pause after a lock before the next piece comes in, in milliseconds
+    pub entry_delay_ms: u64,
+    /// How many times per game the top of the stack is cleared instead of losing
+    pub mercy_count: u32,
+    /// How many lines are cleared by each mercy
+    pub mercy_lines: usize,
+    /// Full lines are cleared only when there are no floating cells above them
+    pub strict_clear: bool,
+    /// Line clears and combos score the same at every level
+    pub flat_scoring: bool,
+    /// Darkens settled cells bordering a differently colored cell
+    pub piece_outlines: bool,
+    /// Bright outline around the falling piece, drawn on the empty cells next to it
+    pub current_outline: bool,
+    /// Low bandwidth mode, skips the shadow, cursor hiding and redraws when nothing changed
+    pub quiet: bool,
+    /// Minimal width of numbers in the info panel, so the layout doesn't shift as they grow
+    pub number_width: usize,
+    /// `horizontal` board has gravity to the right, `vertical` one the conventional way down
+    pub orientation: Orientation,
+    /// `fit` board fills the terminal, `standard` one is 10 cells across and 20 along gravity
+    pub playfield: Playfield,
+    /// Goal of the game, `endless`, `sprint` (40 lines as fast as possible),
+    /// `ultra` (most points in 2 minutes), `garbage` (survive rising garbage lines)
+    /// `marathon` (reach `marathon_level`) or `zen` (no game over)
+    pub mode: GameMode,
+    /// Level ending a marathon game
+    pub marathon_level: u64,
+    /// Pieces fall on their own in zen mode
+    pub zen_gravity: bool,
+    /// Pieces locked between garbage lines in garbage mode
+    pub garbage_interval: u64,
+    /// Gaps in each garbage line
+    pub garbage_holes: usize,
+    /// Counts down from 3 before the game starts
+    pub countdown: bool,
+    /// Plays the daily challenge, same pieces for everyone on a given day
+    pub daily: bool,
+    /// Practice mode, allows `clear_board_key` outside the daily challenge
+    pub practice: bool,
+    /// Key emptying the board in practice mode
+    pub clear_board_key: char,
+    /// Piece held automatically as it spawns while the hold is empty, in practice mode only
+    pub auto_hold: Option<PieceKind>,
+    /// Demo mode, built-in bot plays until a key is pressed
+    pub attract: bool,
+    /// Time on the game over screen without input before the demo starts, in milliseconds, 0 never starts it
+    pub idle_demo_ms: u64,
+    /// Toy mode where the side axis wraps around, pieces leaving one edge appear on the other
+    pub wrap: bool,
+    /// Hard mode where locked blocks fade into the background, the stack has to be remembered
+    pub fading: bool,
+    /// Time locked blocks take to disappear in fading mode, in milliseconds
+    pub fade_ms: u64,
+    /// Colors terminal can show, `auto` detects it from the environment. With fewer than 256
+    /// pieces are marked with glyphs
+    pub color_mode: ColorMode,
+    /// Built-in piece and board colors
+    pub theme: ThemePreset,
+    /// Board border color, theme's one if not set
+    pub border_color: Option<Color>,
+    /// Board background color, theme's one if not set
+    pub background_color: Option<Color>,
+    /// Shadow color, theme's one if not set
+    pub ghost_color: Option<Color>,
+    /// Quitting a game in progress asks for confirmation first
+    pub confirm_quit: bool,
+    /// Rings the terminal bell on line clears and game over, toggled in game with `b`
+    pub bell: bool,
+    /// Board cells drawn twice as large when the terminal fits it, toggled in game with `z`
+    pub big: bool,
+    /// Score, upcoming and held pieces in a single line under the board instead of the panels,
+    /// used anyway on terminals too short for them
+    pub compact_hud: bool,
+    /// Shows last action in the corner of the board, for streaming
+    pub input_echo: bool,
+    /// How long the last action stays visible, in milliseconds
+    pub input_echo_ms: u64,
+    /// Shows a bar filling up until the next gravity step next to the game state
+    pub gravity_bar: bool,
+    /// `dim` grays out the held piece until hold can be used again, `glyph` marks the hold panel
+    /// with a cross, `hidden` shows nothing
+    pub hold_indicator: HoldIndicator,
+    /// Whether identical keys in quick succession are collapsed into one
+    pub input_mode: InputMode,
+    /// Window in which repeated key is ignored in debounced mode, in milliseconds
+    pub debounce_ms: u64,
+    /// Delay before a held move key starts repeating, in milliseconds
+    pub das_ms: u64,
+    /// Interval between repeated moves of a held key, in milliseconds
+    pub arr_ms: u64,
+    /// Tapping a move key twice in quick succession sends the piece to the wall
+    pub double_tap: bool,
+    /// Window for the second tap of a double tap, in milliseconds
+    pub double_tap_ms: u64,
+    /// Moves and rotations kept during the countdown or a line clear, applied once play resumes
+    pub input_buffer: usize,
+    /// How many times faster pieces fall while the soft drop key is held
+    pub soft_drop_factor: u32,
+    /// Most frames drawn per second, 0 draws every change at once
+    pub max_fps: u32,
+    /// Key writing the active config to `tetris-config.toml` in current directory
+    pub dump_config_key: char,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            shown_help_before: false,
+            randomizer: Randomizer::Bag,
+            random_rotation: true,
+            spawn_offset: 0,
+            ghost_support_tint: false,
+            ghost_distance: 0,
+            ghost_gradient: false,
+            spawn_guide: false,
+            danger_distance: 4,
+            start_level: 1,
+            preview_count: 5,
+            next_panel: NextPanel::Strip,
+            lock_delay_ms: 500,
+            lock_reset: LockReset::Move,
+            max_lock_resets: 15,
+            history_length: 50,
+            entry_delay_ms: 150,
+            mercy_count: 0,
+            mercy_lines: 4,
+            strict_clear: false,
+            flat_scoring: false,
+            piece_outlines: false,
+            current_outline: false,
+            quiet: false,
+            number_width: 6,
+            orientation: Orientation::Horizontal,
+            playfield: Playfield::Fit,
+            mode: GameMode::Endless,
+            marathon_level: 15,
+            zen_gravity: false,
+            garbage_interval: 8,
+            garbage_holes: 1,
+            countdown: true,
+            daily: false,
+            attract: false,
+            idle_demo_ms: 30000,
+            wrap: false,
+            fading: false,
+            fade_ms: 3000,
+            color_mode: ColorMode::Auto,
+            theme: ThemePreset::Classic,
+            border_color: None,
+            background_color: None,
+            ghost_color: None,
+            confirm_quit: true,
+            bell: false,
+            big: false,
+            compact_hud: false,
+            input_echo: false,
+            input_echo_ms: 1000,
+            gravity_bar: true,
+            hold_indicator: HoldIndicator::Dim,
+            input_mode: InputMode::Precise,
+            debounce_ms: 30,
+            das_ms: 170,
+            arr_ms: 50,
+            double_tap: false,
+            double_tap_ms: 200,
+            input_buffer: 3,
+            soft_drop_factor: 20,
+            max_fps: 60,
+            dump_config_key: 'o',
+            practice: false,
+            clear_board_key: 'x',
+            auto_hold: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        let dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(dir.join("terminal-tetris").join("config.toml"))
+    }
+
+    /// Options the game engine plays by
+    pub fn rules(&self) -> Rules {
+        Rules {
+            randomizer: self.randomizer,
+            random_rotation: self.random_rotation,
+            spawn_offset: self.spawn_offset,
+            colors: Theme::preset(self.theme).pieces,
+            // custom pieces come from their own file, see `Tetris::set_pieces`
+            pieces: Vec::new(),
+            start_level: self.start_level,
+            preview_count: self.preview_count,
+            lock_delay: Duration::from_millis(self.lock_delay_ms),
+            lock_reset: self.lock_reset,
+            max_lock_resets: self.max_lock_resets,
+            history_length: self.history_length,
+            entry_delay: Duration::from_millis(self.entry_delay_ms),
+            mercy_count: self.mercy_count,
+            mercy_lines: self.mercy_lines,
+            strict_clear: self.strict_clear,
+            flat_scoring: self.flat_scoring,
+            wrap: self.wrap,
+            // a practice aid, the daily challenge can't be practiced on
+            auto_hold: self.auto_hold.filter(|_| self.practice && !self.daily),
+            mode: match self.mode {
+                GameMode::Marathon { .. } => GameMode::Marathon {
+                    level: self.marathon_level,
+                },
+                mode => mode,
+            },
+            garbage_interval: self.garbage_interval,
+            garbage_holes: self.garbage_holes,
+            // cleared lines flash before the stack collapses, except in quiet mode
+            clear_delay: match self.quiet {
+                true => Duration::ZERO,
+                false => Duration::from_millis(200),
+            },
+        }
+    }
+
+    /// Reads config from the default path, falling back to defaults if it's missing or unreadable
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| Self::parse(&text))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        self.save_to(&path)
+    }
+
+    pub fn save_to(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_toml())
+    }
+
+    /// Parses simple `key = value` lines, unknown keys and invalid values are ignored
+    pub fn parse(text: &str) -> Self {
+        let mut config = Self::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            let line = line.split(" #").next().unwrap_or("");
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().trim_matches('"');
+            match key.trim() {
+                "shown_help_before" => set(&mut config.shown_help_before, value),
+                "randomizer" => set(&mut config.randomizer, value),
+                "random_rotation" => set(&mut config.random_rotation, value),
+                "spawn_offset" => set(&mut config.spawn_offset, value),
+                "ghost_support_tint" => set(&mut config.ghost_support_tint, value),
+                "ghost_distance" => set(&mut config.ghost_distance, value),
+                "ghost_gradient" => set(&mut config.ghost_gradient, value),
+                "spawn_guide" => set(&mut config.spawn_guide, value),
+                "danger_distance" => set(&mut config.danger_distance, value),
+                "start_level" => set(&mut config.start_level, value),
+                "preview_count" => set(&mut config.preview_count, value),
+                "next_panel" => set(&mut config.next_panel, value),
+                "lock_delay_ms" => set(&mut config.lock_delay_ms, value),
+                "lock_reset" => set(&mut config.lock_reset, value),
+                "max_lock_resets" => set(&mut config.max_lock_resets, value),
+                "history_length" => set(&mut config.history_length, value),
+                "entry_delay_ms" => set(&mut config.entry_delay_ms, value),
+                "mercy_count" => set(&mut config.mercy_count, value),
+                "mercy_lines" => set(&mut config.mercy_lines, value),
+                "strict_clear" => set(&mut config.strict_clear, value),
+                "flat_scoring" => set(&mut config.flat_scoring, value),
+                "piece_outlines" => set(&mut config.piece_outlines, value),
+                "current_outline" => set(&mut config.current_outline, value),
+                "quiet" => set(&mut config.quiet, value),
+                "number_width" => set(&mut config.number_width, value),
+                "orientation" => set(&mut config.orientation, value),
+                "playfield" => set(&mut config.playfield, value),
+                "mode" => set(&mut config.mode, value),
+                "marathon_level" => set(&mut config.marathon_level, value),
+                "zen_gravity" => set(&mut config.zen_gravity, value),
+                "garbage_interval" => set(&mut config.garbage_interval, value),
+                "garbage_holes" => set(&mut config.garbage_holes, value),
+                "countdown" => set(&mut config.countdown, value),
+                "daily" => set(&mut config.daily, value),
+                "practice" => set(&mut config.practice, value),
+                "clear_board_key" => set(&mut config.clear_board_key, value),
+                "auto_hold" => set_piece(&mut config.auto_hold, value),
+                "attract" => set(&mut config.attract, value),
+                "idle_demo_ms" => set(&mut config.idle_demo_ms, value),
+                "wrap" => set(&mut config.wrap, value),
+                "fading" => set(&mut config.fading, value),
+                "fade_ms" => set(&mut config.fade_ms, value),
+                "color_mode" => set(&mut config.color_mode, value),
+                "theme" => set(&mut config.theme, value),
+                "border_color" => set_color(&mut config.border_color, value),
+                "background_color" => set_color(&mut config.background_color, value),
+                "ghost_color" => set_color(&mut config.ghost_color, value),
+                "confirm_quit" => set(&mut config.confirm_quit, value),
+                "bell" => set(&mut config.bell, value),
+                "big" => set(&mut config.big, value),
+                "compact_hud" => set(&mut config.compact_hud, value),
+                "input_echo" => set(&mut config.input_echo, value),
+                "input_echo_ms" => set(&mut config.input_echo_ms, value),
+                "gravity_bar" => set(&mut config.gravity_bar, value),
+                "hold_indicator" => set(&mut config.hold_indicator, value),
+                "input_mode" => set(&mut config.input_mode, value),
+                "debounce_ms" => set(&mut config.debounce_ms, value),
+                "das_ms" => set(&mut config.das_ms, value),
+                "arr_ms" => set(&mut config.arr_ms, value),
+                "double_tap" => set(&mut config.double_tap, value),
+                "double_tap_ms" => set(&mut config.double_tap_ms, value),
+                "input_buffer" => set(&mut config.input_buffer, value),
+                "soft_drop_factor" => set(&mut config.soft_drop_factor, value),
+                "max_fps" => set(&mut config.max_fps, value),
+                "dump_config_key" => set(&mut config.dump_config_key, value),
+                _ => {}
+            }
+        }
+        config
+    }
+
+    /// Writes every option with a comment describing it, `parse` reads it back
+    pub fn to_toml(&self) -> String {
+        [
+            (
+                "shown_help_before",
+                self.shown_help_before.to_string(),
+                "controls help is collapsed after first run, press ? to show it",
+            ),
+            (
+                "randomizer",
+                format!("\"{}\"", self.randomizer),
+                "\"bag\" deals every shape once before repeating, \"classic\" is fully random",
+            ),
+            (
+                "random_rotation",
+                self.random_rotation.to_string(),
+                "pieces spawn randomly rotated",
+            ),
+            (
+                "spawn_offset",
+                self.spawn_offset.to_string(),
+                "cells the first piece spawns away from the middle, positive is up",
+            ),
+            (
+                "ghost_support_tint",
+                self.ghost_support_tint.to_string(),
+                "shadow cells with a gap beneath them are shown in red",
+            ),
+            (
+                "ghost_distance",
+                self.ghost_distance.to_string(),
+                "shadow is shown only this close to landing, 0 shows it always",
+            ),
+            (
+                "ghost_gradient",
+                self.ghost_gradient.to_string(),
+                "shadow in the piece's color, dimmer the farther it lands",
+            ),
+            (
+                "spawn_guide",
+                self.spawn_guide.to_string(),
+                "marks the area where the next piece will appear",
+            ),
+            (
+                "danger_distance",
+                self.danger_distance.to_string(),
+                "border flashes red when the stack gets this close to the spawn edge, 0 turns it off",
+            ),
+            (
+                "start_level",
+                self.start_level.to_string(),
+                "level the game starts at, higher ones fall faster",
+            ),
+            (
+                "preview_count",
+                self.preview_count.to_string(),
+                "upcoming pieces shown in the next panel",
+            ),
+            (
+                "next_panel",
+                format!("\"{}\"", self.next_panel),
+                "\"strip\" under the board or \"column\" beside it",
+            ),
+            (
+                "lock_delay_ms",
+                self.lock_delay_ms.to_string(),
+                "milliseconds a piece can rest on the stack before locking, 0 locks at once",
+            ),
+            (
+                "lock_reset",
+                format!("\"{}\"", self.lock_reset),
+                "\"classic\" (no restarts), \"step\" (every move restarts the lock delay) or \"move\" (up to max_lock_resets)",
+            ),
+            (
+                "max_lock_resets",
+                self.max_lock_resets.to_string(),
+                "times per piece a move or rotation restarts the lock delay",
+            ),
+            (
+                "history_length",
+                self.history_length.to_string(),
+                "locked pieces undo and the pause menu timeline can go back",
+            ),
+            (
+                "entry_delay_ms",
+                self.entry_delay_ms.to_string(),
+                "milliseconds after a lock before the next piece comes in, a move skips it",
+            ),
+            (
+                "mercy_count",
+                self.mercy_count.to_string(),
+                "times per game the top of the stack is cleared instead of losing",
+            ),
+            (
+                "mercy_lines",
+                self.mercy_lines.to_string(),
+                "lines cleared by each mercy",
+            ),
+            (
+                "strict_clear",
+                self.strict_clear.to_string(),
+                "full lines are cleared only when there are no floating cells above them",
+            ),
+            (
+                "flat_scoring",
+                self.flat_scoring.to_string(),
+                "line clears and combos score the same at every level instead of being multiplied by it",
+            ),
+            (
+                "piece_outlines",
+                self.piece_outlines.to_string(),
+                "darkens edges between touching blocks of different colors",
+            ),
+            (
+                "current_outline",
+                self.current_outline.to_string(),
+                "bright outline around the falling piece, toggled in game with C",
+            ),
+            (
+                "quiet",
+                self.quiet.to_string(),
+                "low bandwidth mode, redraws only when something changes",
+            ),
+            (
+                "number_width",
+                self.number_width.to_string(),
+                "minimal width of score and round numbers",
+            ),
+            (
+                "orientation",
+                format!("\"{}\"", self.orientation),
+                "\"horizontal\" (gravity to the right) or \"vertical\" (pieces fall down)",
+            ),
+            (
+                "playfield",
+                format!("\"{}\"", self.playfield),
+                "\"fit\" (fills the terminal) or \"standard\" (10 across, 20 along gravity)",
+            ),
+            (
+                "mode",
+                format!("\"{}\"", self.mode),
+                "\"endless\", \"sprint\" (clear 40 lines fast), \"ultra\" (score in 2 minutes), \"garbage\" (stack rises from below), \"marathon\" (reach marathon_level) or \"zen\" (no game over)",
+            ),
+            (
+                "marathon_level",
+                self.marathon_level.to_string(),
+                "in marathon mode the game is won on reaching this level",
+            ),
+            (
+                "zen_gravity",
+                self.zen_gravity.to_string(),
+                "pieces fall on their own in zen mode",
+            ),
+            (
+                "garbage_interval",
+                self.garbage_interval.to_string(),
+                "in garbage mode a garbage line rises every this many pieces",
+            ),
+            (
+                "garbage_holes",
+                self.garbage_holes.to_string(),
+                "gaps in each garbage line",
+            ),
+            (
+                "countdown",
+                self.countdown.to_string(),
+                "counts down from 3 before the game starts",
+            ),
+            (
+                "daily",
+                self.daily.to_string(),
+                "daily challenge, same pieces for everyone on a given day",
+            ),
+            (
+                "practice",
+                self.practice.to_string(),
+                "practice mode, clear_board_key empties the board",
+            ),
+            (
+                "clear_board_key",
+                format!("\"{}\"", self.clear_board_key),
+                "key emptying the board in practice mode",
+            ),
+            (
+                "auto_hold",
+                format!(
+                    "\"{}\"",
+                    self.auto_hold.map(|kind| kind.to_string()).unwrap_or_default()
+                ),
+                "piece (I, O, T, S, Z, J, L) held as it spawns into an empty hold in practice mode, empty turns it off",
+            ),
+            (
+                "attract",
+                self.attract.to_string(),
+                "demo mode, built-in bot plays until a key is pressed",
+            ),
+            (
+                "idle_demo_ms",
+                self.idle_demo_ms.to_string(),
+                "milliseconds on the game over screen before the demo starts, 0 never starts it",
+            ),
+            (
+                "wrap",
+                self.wrap.to_string(),
+                "pieces moved off one side appear on the other",
+            ),
+            (
+                "fading",
+                self.fading.to_string(),
+                "hard mode, locked blocks fade into the background",
+            ),
+            (
+                "fade_ms",
+                self.fade_ms.to_string(),
+                "milliseconds locked blocks take to disappear in fading mode",
+            ),
+            (
+                "color_mode",
+                format!("\"{}\"", self.color_mode),
+                "\"auto\", \"256\", \"16\" or \"mono\", pieces get glyphs with 16 or mono",
+            ),
+            (
+                "theme",
+                format!("\"{}\"", self.theme),
+                "\"classic\", \"high-contrast\", \"random\" or \"color-blind\" piece colors",
+            ),
+            (
+                "border_color",
+                color_toml(self.border_color),
+                "board border color (name, 0-255 index or #rrggbb), empty uses the theme's",
+            ),
+            (
+                "background_color",
+                color_toml(self.background_color),
+                "board background color, empty uses the theme's",
+            ),
+            (
+                "ghost_color",
+                color_toml(self.ghost_color),
+                "shadow color, empty uses the theme's",
+            ),
+            (
+                "confirm_quit",
+                self.confirm_quit.to_string(),
+                "quitting a game in progress asks for confirmation",
+            ),
+            (
+                "bell",
+                self.bell.to_string(),
+                "rings the terminal bell on line clears and game over",
+            ),
+            (
+                "big",
+                self.big.to_string(),
+                "board cells drawn twice as large when the terminal fits it",
+            ),
+            (
+                "compact_hud",
+                self.compact_hud.to_string(),
+                "single status line under the board instead of the panels, used anyway on short terminals",
+            ),
+            (
+                "input_echo",
+                self.input_echo.to_string(),
+                "shows last action in the corner of the board",
+            ),
+            (
+                "input_echo_ms",
+                self.input_echo_ms.to_string(),
+                "milliseconds the last action stays visible",
+            ),
+            (
+                "gravity_bar",
+                self.gravity_bar.to_string(),
+                "bar filling up until the next gravity step",
+            ),
+            (
+                "hold_indicator",
+                format!("\"{}\"", self.hold_indicator),
+                "\"dim\" grays out a used hold, \"glyph\" marks it with a cross, \"hidden\" shows nothing",
+            ),
+            (
+                "input_mode",
+                format!("\"{}\"", self.input_mode),
+                "\"precise\" handles every key, \"debounced\" ignores quickly repeated keys",
+            ),
+            (
+                "debounce_ms",
+                self.debounce_ms.to_string(),
+                "milliseconds in which a repeated key is ignored in debounced mode",
+            ),
+            (
+                "das_ms",
+                self.das_ms.to_string(),
+                "milliseconds before a held move key starts repeating",
+            ),
+            (
+                "arr_ms",
+                self.arr_ms.to_string(),
+                "milliseconds between repeated moves of a held key",
+            ),
+            (
+                "double_tap",
+                self.double_tap.to_string(),
+                "tapping a move key twice sends the piece to the wall",
+            ),
+            (
+                "double_tap_ms",
+                self.double_tap_ms.to_string(),
+                "milliseconds in which the second tap of a double tap has to come",
+            ),
+            (
+                "input_buffer",
+                self.input_buffer.to_string(),
+                "moves and rotations kept during the countdown or a line clear, 0 drops them",
+            ),
+            (
+                "soft_drop_factor",
+                self.soft_drop_factor.to_string(),
+                "how many times faster pieces fall while the soft drop key is held",
+            ),
+            (
+                "max_fps",
+                self.max_fps.to_string(),
+                "most frames drawn per second, 0 draws every change at once",
+            ),
+            (
+                "dump_config_key",
+                format!("\"{}\"", self.dump_config_key),
+                "key writing active config to tetris-config.toml",
+            ),
+        ]
+        .iter()
+        .map(|(key, value, comment)| format!("# {comment}\n{key} = {value}\n"))
+        .collect()
+    }
+}
+
+/// Empty value means the color isn't overridden
+fn set_color(field: &mut Option<Color>, value: &str) {
+    if value.is_empty() {
+        *field = None;
+    } else if let Ok(color) = value.parse() {
+        *field = Some(color);
+    }
+}
+
+/// Empty value turns auto hold off
+fn set_piece(field: &mut Option<PieceKind>, value: &str) {
+    if value.is_empty() {
+        *field = None;
+    } else if let Ok(kind) = value.parse() {
+        *field = Some(kind);
+    }
+}
+
+fn color_toml(color: Option<Color>) -> String {
+    format!("\"{}\"", color.map(|c| c.to_string()).unwrap_or_default())
+}
+
+fn set<T: FromStr>(field: &mut T, value: &str) {
+    if let Ok(value) = value.parse() {
+        *field = value;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dumped_config_loads_back_the_same() {
+        let config = Config {
+            shown_help_before: true,
+            randomizer: Randomizer::Classic,
+            spawn_offset: -2,
+            ghost_distance: 3,
+            lock_reset: LockReset::Step,
+            next_panel: NextPanel::Column,
+            orientation: Orientation::Vertical,
+            playfield: Playfield::Standard,
+            mode: GameMode::Marathon { level: 15 },
+            marathon_level: 20,
+            practice: true,
+            clear_board_key: 'c',
+            auto_hold: Some(PieceKind::T),
+            color_mode: ColorMode::Basic,
+            theme: ThemePreset::ColorBlind,
+            border_color: Some(Color::Rgb(255, 136, 0)),
+            ghost_color: Some(Color::Indexed(240)),
+            input_mode: InputMode::Debounced,
+            hold_indicator: HoldIndicator::Glyph,
+            current_outline: true,
+            dump_config_key: 'p',
+            ..Config::default()
+        };
+        assert_ne!(config, Config::default());
+        assert_eq!(Config::parse(&config.to_toml()), config);
+    }
+}
@@ -0,0 +1,84 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Daily challenge, everyone playing on the same (UTC) day gets the same pieces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Daily {
+    pub date: String,
+    pub seed: u64,
+}
+
+impl Daily {
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self::from_days(secs / 86400)
+    }
+
+    /// Builds the challenge for given number of days since 1970-01-01,
+    /// seed is the date written as `yyyymmdd` number
+    pub fn from_days(days: u64) -> Self {
+        let (year, month, day) = civil_from_days(days);
+        Self {
+            date: format!("{year:04}-{month:02}-{day:02}"),
+            seed: year * 10000 + month * 100 + day,
+        }
+    }
+}
+
+/// Converts days since unix epoch to (year, month, day) in the gregorian calendar
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tetris_engine::engine::{GameEngine, Rules};
+
+    #[test]
+    fn seed_is_the_date() {
+        assert_eq!(
+            Daily::from_days(19782),
+            Daily {
+                date: "2024-02-29".to_string(),
+                seed: 20240229,
+            }
+        );
+        assert_eq!(Daily::from_days(0).seed, 19700101);
+        assert_eq!(Daily::from_days(11017).date, "2000-03-01");
+    }
+
+    #[test]
+    fn same_day_deals_the_same_pieces() {
+        let pieces = |daily: Daily| {
+            let mut engine = GameEngine::new(Rules::default(), 40, 10, daily.seed);
+            (0..20)
+                .map(|_| {
+                    let kind = engine.current_block.kind;
+                    engine.move_till_end();
+                    kind
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            pieces(Daily::from_days(19782)),
+            pieces(Daily::from_days(19782))
+        );
+        assert_ne!(
+            pieces(Daily::from_days(19782)),
+            pieces(Daily::from_days(19783))
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use super::key_bindings::key_name;
+use crossterm::event::{KeyEvent, KeyEventKind};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::Stylize,
+    text,
+    widgets::{Block, Clear, Paragraph, Widget},
+};
+use std::collections::VecDeque;
+
+/// Key events kept in the log
+const EVENT_COUNT: usize = 8;
+
+/// Last key events and the state timing depends on, drawn in the corner of the board.
+/// Built only with the `debug-overlay` feature
+#[derive(Debug, Default)]
+pub struct DebugOverlay {
+    pub visible: bool,
+    events: VecDeque<KeyEvent>,
+}
+
+impl DebugOverlay {
+    /// Keeps the event, presses, repeats and releases alike
+    pub fn record(&mut self, event: KeyEvent) {
+        if self.events.len() == EVENT_COUNT {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// Game state lines followed by the key events, the latest one last
+    pub fn render(&self, state: Vec<text::Line>, area: Rect, buf: &mut Buffer) {
+        let mut lines = state;
+        lines.push(text::Line::from(" Keys:".white()));
+        for event in &self.events {
+            let name = key_name(event).unwrap_or_else(|| format!("{:?}", event.code));
+            let kind = match event.kind {
+                KeyEventKind::Press => "press",
+                KeyEventKind::Repeat => "repeat",
+                KeyEventKind::Release => "release",
+            };
+            lines.push(text::Line::from(vec![
+                format!("  {name:<10}").bold().yellow(),
+                kind.dark_gray(),
+            ]));
+        }
+
+        let inner = Block::bordered().inner(area);
+        let overlay = Rect {
+            x: inner.x,
+            y: inner.y,
+            width: inner.width.min(28),
+            height: inner.height.min(lines.len() as u16 + 2),
+        };
+        Clear.render(overlay, buf);
+        Paragraph::new(lines)
+            .block(Block::bordered().title_top(" Debug ".bold().cyan()))
+            .render(overlay, buf);
+    }
+}
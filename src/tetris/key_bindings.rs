@@ -0,0 +1,237 @@
+use super::config::{Config, Orientation};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+/// Gameplay action a key can be bound to, named in the key bindings file as in `Action::name`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Rotate,
+    SoftDrop,
+    MoveUp,
+    MoveDown,
+    HardDrop,
+    Hold,
+    Pause,
+    Save,
+    Quit,
+}
+
+impl Action {
+    const ALL: [Action; 9] = [
+        Action::Rotate,
+        Action::SoftDrop,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::HardDrop,
+        Action::Hold,
+        Action::Pause,
+        Action::Save,
+        Action::Quit,
+    ];
+
+    /// Action with the name used in the key bindings file and replays
+    pub fn from_name(name: &str) -> Option<Self> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    /// Name used in the key bindings file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Rotate => "rotate",
+            Action::SoftDrop => "soft_drop",
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::HardDrop => "hard_drop",
+            Action::Hold => "hold",
+            Action::Pause => "pause",
+            Action::Save => "save",
+            Action::Quit => "quit",
+        }
+    }
+
+    /// Keys matching the board orientation, moving up the side axis is right on a vertical board
+    fn default_keys(self, orientation: Orientation) -> &'static str {
+        match (self, orientation) {
+            (Action::Rotate, Orientation::Horizontal) => "left, a",
+            (Action::SoftDrop, Orientation::Horizontal) => "right, d",
+            (Action::MoveUp, Orientation::Horizontal) => "up, w",
+            (Action::MoveDown, Orientation::Horizontal) => "down, s",
+            (Action::Rotate, Orientation::Vertical) => "up, w",
+            (Action::SoftDrop, Orientation::Vertical) => "down, s",
+            (Action::MoveUp, Orientation::Vertical) => "right, d",
+            (Action::MoveDown, Orientation::Vertical) => "left, a",
+            (Action::HardDrop, _) => "space",
+            (Action::Hold, _) => "h",
+            (Action::Pause, _) => "p",
+            (Action::Save, _) => "ctrl+s",
+            (Action::Quit, _) => "ctrl+c",
+        }
+    }
+}
+
+/// Key with an optional control modifier, e.g. `ctrl+c`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Key {
+    code: KeyCode,
+    ctrl: bool,
+}
+
+impl Key {
+    fn parse(name: &str) -> Option<Self> {
+        let (ctrl, name) = match name.strip_prefix("ctrl+") {
+            Some(name) => (true, name),
+            None => (false, name),
+        };
+        let code = match name {
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "space" => KeyCode::Char(' '),
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            _ => {
+                let mut chars = name.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => return None,
+                }
+            }
+        };
+        Some(Self { code, ctrl })
+    }
+}
+
+/// Keys of an action in the key bindings file, written as `"key, key"`
+#[derive(Debug, Deserialize)]
+#[serde(try_from = "String")]
+struct KeyList(Vec<Key>);
+
+impl TryFrom<String> for KeyList {
+    type Error = String;
+
+    fn try_from(keys: String) -> Result<Self, Self::Error> {
+        keys.split(',')
+            .map(|key| {
+                Key::parse(key.trim()).ok_or_else(|| format!("invalid key `{}`", key.trim()))
+            })
+            .collect::<Result<_, _>>()
+            .map(KeyList)
+    }
+}
+
+/// Name of the key as written in the key bindings file, the inverse of parsing it
+pub fn key_name(event: &KeyEvent) -> Option<String> {
+    let name = match event.code {
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        _ => return None,
+    };
+    if event.modifiers.contains(KeyModifiers::CONTROL) {
+        Some(format!("ctrl+{name}"))
+    } else {
+        Some(name)
+    }
+}
+
+/// Keys triggering each action, read from `keys.toml` next to the config file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBindings {
+    bindings: Vec<(Key, Action)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::defaults(Orientation::Horizontal)
+    }
+}
+
+impl KeyBindings {
+    pub fn defaults(orientation: Orientation) -> Self {
+        let mut bindings = Vec::new();
+        for action in Action::ALL {
+            for name in action.default_keys(orientation).split(',') {
+                bindings.push((Key::parse(name.trim()).unwrap(), action));
+            }
+        }
+        Self { bindings }
+    }
+
+    pub fn path() -> Option<PathBuf> {
+        Config::path().map(|path| path.with_file_name("keys.toml"))
+    }
+
+    /// Reads bindings from the default path, missing file gives the default bindings
+    pub fn load(orientation: Orientation) -> Result<Self, String> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::defaults(orientation));
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text, orientation)
+                .map_err(|error| format!("{}: {error}", path.display())),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                Ok(Self::defaults(orientation))
+            }
+            Err(error) => Err(format!("Can't read {}: {error}", path.display())),
+        }
+    }
+
+    /// Parses a TOML table of `action = "key, key"`, actions not listed keep their default keys
+    pub fn parse(text: &str, orientation: Orientation) -> Result<Self, String> {
+        let file: BTreeMap<Action, KeyList> =
+            toml::from_str(text).map_err(|error| error.to_string())?;
+        let mut bindings = Self::defaults(orientation).bindings;
+        for (action, KeyList(keys)) in file {
+            bindings.retain(|(_, bound)| *bound != action);
+            bindings.extend(keys.into_iter().map(|key| (key, action)));
+        }
+        Ok(Self { bindings })
+    }
+
+    pub fn action(&self, event: &KeyEvent) -> Option<Action> {
+        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+        self.bindings
+            .iter()
+            .find(|(key, _)| key.code == event.code && key.ctrl == ctrl)
+            .map(|(_, action)| *action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn listed_actions_replace_their_default_keys() {
+        let text = "# TOML comments are fine\nrotate = \"r\"\nhard_drop = \"enter, x\"\n";
+        let keys = KeyBindings::parse(text, Orientation::Horizontal).unwrap();
+        assert_eq!(keys.action(&key(KeyCode::Char('r'))), Some(Action::Rotate));
+        assert_eq!(keys.action(&key(KeyCode::Left)), None);
+        assert_eq!(
+            keys.action(&key(KeyCode::Char('x'))),
+            Some(Action::HardDrop)
+        );
+        assert_eq!(keys.action(&key(KeyCode::Char('h'))), Some(Action::Hold));
+
+        let error = KeyBindings::parse("hold = \"h, ctrl+\"", Orientation::Horizontal).unwrap_err();
+        assert!(error.contains("invalid key `ctrl+`"), "{error}");
+        assert!(KeyBindings::parse("jump = \"j\"", Orientation::Horizontal).is_err());
+        assert!(KeyBindings::parse("hold = h", Orientation::Horizontal).is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use super::config::Config;
+use std::{fs, io, path::PathBuf};
+use tetris_engine::tetris_block::PieceDef;
+
+/// Most pieces a set can have, each is told apart by a byte
+const MAX_PIECES: usize = 256;
+
+/// Custom piece set, `pieces.txt` next to the config file
+pub fn path() -> Option<PathBuf> {
+    Config::path().map(|path| path.with_file_name("pieces.txt"))
+}
+
+/// Reads pieces from the default path, without the file the game plays with tetrominoes
+pub fn load() -> Result<Vec<PieceDef>, String> {
+    let Some(path) = path() else {
+        return Ok(Vec::new());
+    };
+    match fs::read_to_string(&path) {
+        Ok(text) => parse(&text).map_err(|error| format!("{}: {error}", path.display())),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(error) => Err(format!("Can't read {}: {error}", path.display())),
+    }
+}
+
+/// Parses pieces each starting with a `piece <name> [color]` line, color being a 256 color
+/// palette index. Its pattern lines follow from the one closest to the spawn edge, drawn across
+/// the board like in board files: `X` is a filled cell, `O` and `.` are empty
+pub fn parse(text: &str) -> Result<Vec<PieceDef>, String> {
+    let mut pieces = Vec::new();
+    let mut current: Option<(String, Option<u8>, Vec<Vec<bool>>)> = None;
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix("piece ") {
+            if let Some((name, color, pattern)) = current.take() {
+                pieces.push(PieceDef::new(&name, pattern, color)?);
+            }
+            let mut parts = header.split_whitespace();
+            let Some(name) = parts.next().map(str::to_string) else {
+                return Err(format!("line {}: piece without a name", number + 1));
+            };
+            let color = match parts.next() {
+                Some(color) => Some(color.parse().map_err(|_| {
+                    format!("line {}: invalid color `{color}` for {name}", number + 1)
+                })?),
+                None => None,
+            };
+            current = Some((name, color, Vec::new()));
+            continue;
+        }
+        let Some((_, _, pattern)) = current.as_mut() else {
+            return Err(format!(
+                "line {}: expected `piece <name> [color]` before pattern lines",
+                number + 1
+            ));
+        };
+        let cells = line
+            .chars()
+            .map(|c| match c {
+                'X' => Ok(true),
+                'O' | '.' => Ok(false),
+                _ => Err(format!(
+                    "line {}: unknown cell `{c}`, expected X, O or .",
+                    number + 1
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        pattern.push(cells);
+    }
+    if let Some((name, color, pattern)) = current {
+        pieces.push(PieceDef::new(&name, pattern, color)?);
+    }
+    if pieces.len() > MAX_PIECES {
+        return Err(format!(
+            "{} pieces, at most {MAX_PIECES} are allowed",
+            pieces.len()
+        ));
+    }
+    Ok(pieces)
+}
@@ -0,0 +1,244 @@
+use super::config::Config;
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+    thread,
+    time::Duration,
+};
+use tetris_engine::tetris_block::PieceDef;
+
+/// How often a replay that isn't fully written yet is checked for more
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Writes everything needed to play a game again: the seed, board size and config in the header,
+/// then each input and gravity step as `<milliseconds since start> <event>` lines
+#[derive(Debug)]
+pub struct Recorder {
+    out: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            out: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    /// Header of the replay, config options are written as `config <key> = <value>` lines
+    pub fn start(&mut self, seed: u64, board: (usize, usize), config: &Config) -> io::Result<()> {
+        writeln!(self.out, "# terminal-tetris replay")?;
+        writeln!(self.out, "seed {seed}")?;
+        writeln!(self.out, "board {} {}", board.0, board.1)?;
+        for line in config.to_toml().lines() {
+            if !line.is_empty() && !line.starts_with('#') {
+                writeln!(self.out, "config {line}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stack the games start with, as `stack <cells>` lines from the spawn edge to the floor,
+    /// `.` for an empty cell
+    pub fn stack(&mut self, lines: &[Vec<Option<u8>>]) -> io::Result<()> {
+        for line in lines {
+            let cells: Vec<String> = line
+                .iter()
+                .map(|cell| cell.map_or(".".to_string(), |color| color.to_string()))
+                .collect();
+            writeln!(self.out, "stack {}", cells.join(" "))?;
+        }
+        Ok(())
+    }
+
+    /// Custom piece set as `piece <name> <color> <pattern>` lines,
+    /// pattern columns separated by `/` and `.` for a random color
+    pub fn pieces(&mut self, pieces: &[PieceDef]) -> io::Result<()> {
+        for piece in pieces {
+            let color = piece
+                .color
+                .map_or(".".to_string(), |color| color.to_string());
+            let pattern: Vec<String> = piece
+                .pattern
+                .iter()
+                .map(|col| col.iter().map(|c| if *c { 'X' } else { 'O' }).collect())
+                .collect();
+            writeln!(
+                self.out,
+                "piece {} {color} {}",
+                piece.name,
+                pattern.join("/")
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Written right away, so the game can be watched as it's played
+    pub fn event(&mut self, time: Duration, event: &str) -> io::Result<()> {
+        writeln!(self.out, "{} {event}", time.as_millis())?;
+        self.out.flush()
+    }
+
+    /// Last line of a replay, telling whoever watches it that the game is over
+    pub fn finish(&mut self, time: Duration) -> io::Result<()> {
+        self.event(time, "end")
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Everything written before the first event, enough to set up the same game
+#[derive(Debug)]
+pub struct Header {
+    pub seed: u64,
+    /// Board cells along and across gravity
+    pub board: (usize, usize),
+    pub config: Config,
+    /// Stack the games start with, from the spawn edge to the floor
+    pub stack: Vec<Vec<Option<u8>>>,
+    pub pieces: Vec<PieceDef>,
+}
+
+impl Header {
+    /// Parses the lines `Recorder` writes before the events
+    fn parse(lines: &[String]) -> Result<Self, String> {
+        let (mut seed, mut board) = (None, None);
+        let mut config = String::new();
+        let (mut stack, mut pieces) = (Vec::new(), Vec::new());
+        for (number, line) in lines.iter().enumerate() {
+            let invalid = || format!("line {}: invalid `{line}`", number + 1);
+            if line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once(' ').ok_or_else(invalid)?;
+            match key {
+                "seed" => seed = Some(value.parse().map_err(|_| invalid())?),
+                "board" => {
+                    let (width, height) = value.split_once(' ').ok_or_else(invalid)?;
+                    board = Some((
+                        width.parse().map_err(|_| invalid())?,
+                        height.parse().map_err(|_| invalid())?,
+                    ));
+                }
+                "config" => {
+                    config.push_str(value);
+                    config.push('\n');
+                }
+                "stack" => stack.push(
+                    value
+                        .split(' ')
+                        .map(|cell| match cell {
+                            "." => Ok(None),
+                            color => color.parse().map(Some).map_err(|_| invalid()),
+                        })
+                        .collect::<Result<_, _>>()?,
+                ),
+                "piece" => {
+                    let mut parts = value.split(' ');
+                    let (Some(name), Some(color), Some(pattern)) =
+                        (parts.next(), parts.next(), parts.next())
+                    else {
+                        return Err(invalid());
+                    };
+                    let color = match color {
+                        "." => None,
+                        color => Some(color.parse().map_err(|_| invalid())?),
+                    };
+                    let pattern = pattern
+                        .split('/')
+                        .map(|col| col.chars().map(|c| c == 'X').collect())
+                        .collect();
+                    pieces.push(PieceDef::new(name, pattern, color)?);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(Self {
+            seed: seed.ok_or("missing seed")?,
+            board: board.ok_or("missing board size")?,
+            config: Config::parse(&config),
+            stack,
+            pieces,
+        })
+    }
+}
+
+/// Reads a replay while it's still being written, like `tail -f`.
+/// Only whole lines are taken, one still being written waits for the rest of it
+#[derive(Debug)]
+pub struct Follower {
+    file: File,
+    /// Start of a line that isn't fully written yet
+    partial: Vec<u8>,
+    lines: VecDeque<String>,
+    ended: bool,
+}
+
+impl Follower {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            file: File::open(path)?,
+            partial: Vec::new(),
+            lines: VecDeque::new(),
+            ended: false,
+        })
+    }
+
+    /// Takes whole lines written since the last read
+    fn read(&mut self) -> io::Result<()> {
+        self.file.read_to_end(&mut self.partial)?;
+        while let Some(end) = self.partial.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.partial.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line).trim().to_string();
+            if !line.is_empty() {
+                self.lines.push_back(line);
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits for the header, it's complete once the first event follows it
+    pub fn header(&mut self) -> Result<Header, String> {
+        let is_event = |line: &String| line.starts_with(|c: char| c.is_ascii_digit());
+        loop {
+            self.read().map_err(|error| error.to_string())?;
+            if let Some(first_event) = self.lines.iter().position(is_event) {
+                let header: Vec<String> = self.lines.drain(..first_event).collect();
+                return Header::parse(&header);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Events written since the last call, as time since the game started and the event,
+    /// up to the end of the replay
+    pub fn events(&mut self) -> io::Result<Vec<(Duration, String)>> {
+        if self.ended {
+            return Ok(Vec::new());
+        }
+        self.read()?;
+        let mut events = Vec::new();
+        while let Some(line) = self.lines.pop_front() {
+            let Some((time, event)) = line.split_once(' ') else {
+                continue;
+            };
+            let Ok(time) = time.parse() else {
+                continue;
+            };
+            if event == "end" {
+                self.ended = true;
+                break;
+            }
+            events.push((Duration::from_millis(time), event.to_string()));
+        }
+        Ok(events)
+    }
+
+    /// The game was over when the replay was finished
+    pub fn ended(&self) -> bool {
+        self.ended
+    }
+}
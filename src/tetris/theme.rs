@@ -0,0 +1,97 @@
+use super::config::Config;
+use ratatui::style::Color;
+use std::{fmt, str::FromStr};
+use tetris_engine::tetris_block::SHAPES;
+
+/// Built-in set of colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemePreset {
+    /// Each shape in its usual color
+    Classic,
+    /// Bright colors far apart from each other, on a plain black board
+    HighContrast,
+    /// Every piece in a random bright color
+    Random,
+    /// Okabe-Ito colors, told apart with red-green color blindness (deuteranopia and protanopia)
+    /// by brightness as well as hue
+    ColorBlind,
+}
+
+/// Colors of pieces and the board
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Palette index of each shape's color, `None` picks a random one for every piece
+    pub pieces: Option<[u8; SHAPES]>,
+    pub border: Color,
+    pub background: Color,
+    pub ghost: Color,
+}
+
+impl Theme {
+    pub fn preset(preset: ThemePreset) -> Self {
+        match preset {
+            ThemePreset::Classic => Self {
+                // I, O, T, S, Z, J, L
+                pieces: Some([14, 11, 13, 10, 9, 12, 208]),
+                border: Color::Gray,
+                background: Color::Black,
+                ghost: Color::DarkGray,
+            },
+            ThemePreset::HighContrast => Self {
+                // white, light yellow, magenta, green, red, blue and cyan
+                pieces: Some([15, 11, 13, 10, 9, 12, 14]),
+                border: Color::White,
+                background: Color::Black,
+                ghost: Color::Gray,
+            },
+            ThemePreset::Random => Self {
+                pieces: None,
+                ..Self::preset(ThemePreset::Classic)
+            },
+            ThemePreset::ColorBlind => Self {
+                // sky blue, yellow, reddish purple, bluish green, vermillion, blue and orange
+                pieces: Some([117, 227, 175, 36, 166, 25, 214]),
+                border: Color::White,
+                background: Color::Black,
+                // neutral and darker than every piece, so it isn't mistaken for one
+                ghost: Color::Indexed(240),
+            },
+        }
+    }
+
+    /// Preset chosen in config with its board colors overridden where set
+    pub fn from_config(config: &Config) -> Self {
+        let theme = Self::preset(config.theme);
+        Self {
+            border: config.border_color.unwrap_or(theme.border),
+            background: config.background_color.unwrap_or(theme.background),
+            ghost: config.ghost_color.unwrap_or(theme.ghost),
+            ..theme
+        }
+    }
+}
+
+impl FromStr for ThemePreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "classic" => Ok(ThemePreset::Classic),
+            "high-contrast" => Ok(ThemePreset::HighContrast),
+            "random" => Ok(ThemePreset::Random),
+            "color-blind" => Ok(ThemePreset::ColorBlind),
+            _ => Err(format!("unknown theme {s}")),
+        }
+    }
+}
+
+impl fmt::Display for ThemePreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ThemePreset::Classic => "classic",
+            ThemePreset::HighContrast => "high-contrast",
+            ThemePreset::Random => "random",
+            ThemePreset::ColorBlind => "color-blind",
+        })
+    }
+}
@@ -0,0 +1,354 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// Number of different shapes
+pub const SHAPES: usize = 7;
+
+/// Most cells a custom piece spans along either axis, bigger ones don't fit the preview slots
+pub const MAX_PIECE_SIZE: usize = 5;
+
+/// Tetromino a block is, in shape index order, or a piece of a custom set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PieceKind {
+    I,
+    O,
+    T,
+    S,
+    Z,
+    J,
+    L,
+    /// Index into `Rules::pieces`
+    Custom(u8),
+}
+
+impl PieceKind {
+    pub const ALL: [PieceKind; SHAPES] = [
+        PieceKind::I,
+        PieceKind::O,
+        PieceKind::T,
+        PieceKind::S,
+        PieceKind::Z,
+        PieceKind::J,
+        PieceKind::L,
+    ];
+
+    /// Shape index, `0..SHAPES` for tetrominoes, custom pieces follow them
+    pub fn index(self) -> usize {
+        match self {
+            PieceKind::I => 0,
+            PieceKind::O => 1,
+            PieceKind::T => 2,
+            PieceKind::S => 3,
+            PieceKind::Z => 4,
+            PieceKind::J => 5,
+            PieceKind::L => 6,
+            PieceKind::Custom(index) => SHAPES + index as usize,
+        }
+    }
+
+    /// Kind with the shape index, the inverse of `index`
+    pub fn from_index(index: usize) -> Option<Self> {
+        match index.checked_sub(SHAPES) {
+            None => Some(Self::ALL[index]),
+            Some(custom) => u8::try_from(custom).ok().map(PieceKind::Custom),
+        }
+    }
+}
+
+impl fmt::Display for PieceKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PieceKind::Custom(index) => write!(f, "#{}", index + 1),
+            kind => write!(f, "{kind:?}"),
+        }
+    }
+}
+
+impl FromStr for PieceKind {
+    type Err = String;
+
+    /// Tetromino letter, or `#n` for the n-th piece of a custom set as `Display` writes it
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(number) = s.strip_prefix('#') {
+            return match number.parse::<u8>() {
+                Ok(number) if number > 0 => Ok(PieceKind::Custom(number - 1)),
+                _ => Err(format!("unknown piece {s}")),
+            };
+        }
+        PieceKind::ALL
+            .into_iter()
+            .find(|kind| kind.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown piece {s}"))
+    }
+}
+
+/// Piece of a custom set, like a pentomino
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PieceDef {
+    pub name: String,
+    /// Columns from the spawn edge towards the floor, like `TetrisBlock::pattern`
+    pub pattern: Vec<Vec<bool>>,
+    /// Index into the 256 color palette, `None` picks a random one for every piece
+    pub color: Option<u8>,
+}
+
+impl PieceDef {
+    /// Checks the pattern has a filled cell and fits within `MAX_PIECE_SIZE` cells,
+    /// empty columns and rows around the filled cells are trimmed
+    pub fn new(name: &str, pattern: Vec<Vec<bool>>, color: Option<u8>) -> Result<Self, String> {
+        let filled: Vec<usize> = (0..pattern.len())
+            .filter(|i| pattern[*i].iter().any(|c| *c))
+            .collect();
+        let (Some(&first), Some(&last)) = (filled.first(), filled.last()) else {
+            return Err(format!("piece {name} has no filled cells"));
+        };
+        let start = pattern
+            .iter()
+            .filter_map(|col| col.iter().position(|c| *c))
+            .min()
+            .unwrap_or(0);
+        let end = pattern
+            .iter()
+            .filter_map(|col| col.iter().rposition(|c| *c))
+            .max()
+            .unwrap_or(0);
+
+        let pattern: Vec<Vec<bool>> = pattern[first..=last]
+            .iter()
+            .map(|col| (start..=end).map(|j| col.get(j) == Some(&true)).collect())
+            .collect();
+        if pattern.len() > MAX_PIECE_SIZE || pattern[0].len() > MAX_PIECE_SIZE {
+            return Err(format!(
+                "piece {name} is {}x{} cells, at most {MAX_PIECE_SIZE}x{MAX_PIECE_SIZE} are allowed",
+                pattern.len(),
+                pattern[0].len()
+            ));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            pattern,
+            color,
+        })
+    }
+}
+
+/// Super Rotation System offsets tried in order when rotating from orientation `n` to `n + 1`,
+/// as (right, up) with gravity pointing down
+const KICKS: [[(i32, i32); 5]; 4] = [
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+];
+
+const I_KICKS: [[(i32, i32); 5]; 4] = [
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TetrisBlock {
+    /// Index into the 256 color palette
+    pub color: u8,
+    pub pos: (i32, i32),
+    pub pattern: Vec<Vec<bool>>,
+    /// Same for every rotation of the block
+    pub kind: PieceKind,
+    /// Number of quarter turns from the spawn pattern, `0..4`
+    pub orientation: usize,
+}
+
+impl TetrisBlock {
+    /// Block of given shape (`0..SHAPES`) with random color, optionally randomly rotated
+    pub fn from_shape(shape: usize, rng: &mut impl Rng, random_rotation: bool) -> Self {
+        let kind = PieceKind::ALL[shape % SHAPES];
+        let pattern: Vec<Vec<bool>> = match kind {
+            PieceKind::I => "XXXX",
+            PieceKind::O => "XX\nXX",
+            PieceKind::T => "XXX\nOXO",
+            PieceKind::S => "OXX\nXXO",
+            PieceKind::Z => "XXO\nOXX",
+            PieceKind::J => "XXX\nOOX",
+            PieceKind::L => "OOX\nXXX",
+            PieceKind::Custom(_) => unreachable!("only tetrominoes are in `PieceKind::ALL`"),
+        }
+        .lines()
+        .map(|l| l.chars().map(|c| c == 'X').collect())
+        .collect();
+        Self::with_pattern(kind, pattern, rng, random_rotation)
+    }
+
+    /// Block of the custom piece at `index` of its set, in the piece's color
+    /// or a random one, optionally randomly rotated
+    pub fn from_def(def: &PieceDef, index: u8, rng: &mut impl Rng, random_rotation: bool) -> Self {
+        let mut block = Self::with_pattern(
+            PieceKind::Custom(index),
+            def.pattern.clone(),
+            rng,
+            random_rotation,
+        );
+        if let Some(color) = def.color {
+            block.color = color;
+        }
+        block
+    }
+
+    fn with_pattern(
+        kind: PieceKind,
+        mut pattern: Vec<Vec<bool>>,
+        rng: &mut impl Rng,
+        random_rotation: bool,
+    ) -> Self {
+        let color = rng.gen_range(9..=14);
+
+        let mut orientation = 0;
+        if random_rotation {
+            orientation = rng.gen_range(0..4);
+            for _ in 0..orientation {
+                pattern = TetrisBlock::rotate90(&pattern);
+            }
+        }
+
+        Self {
+            color,
+            pattern,
+            pos: (0, 0),
+            kind,
+            orientation,
+        }
+    }
+
+    pub fn is_t(&self) -> bool {
+        self.kind == PieceKind::T
+    }
+
+    /// Pattern cell with three filled neighbours, the one a T piece turns around
+    pub fn center(&self) -> Option<(usize, usize)> {
+        let filled =
+            |i: usize, j: usize| self.pattern.get(i).and_then(|col| col.get(j)) == Some(&true);
+        (0..self.pattern.len())
+            .flat_map(|i| (0..self.pattern[i].len()).map(move |j| (i, j)))
+            .find(|&(i, j)| {
+                filled(i, j)
+                    && [
+                        i > 0 && filled(i - 1, j),
+                        filled(i + 1, j),
+                        j > 0 && filled(i, j - 1),
+                        filled(i, j + 1),
+                    ]
+                    .iter()
+                    .filter(|n| **n)
+                    .count()
+                        == 3
+            })
+    }
+
+    /// Position offsets to try when rotating, mapped to the board axes
+    /// (gravity along x, so up is towards the spawn edge)
+    pub fn kicks(&self) -> impl Iterator<Item = (i32, i32)> {
+        let table = if self.kind == PieceKind::I {
+            &I_KICKS
+        } else {
+            &KICKS
+        };
+        table[self.orientation % 4]
+            .iter()
+            .map(|(right, up)| (-up, *right))
+    }
+
+    pub fn rotate90(pattern: &[Vec<bool>]) -> Vec<Vec<bool>> {
+        let width = 1.max(pattern.len());
+        let height = 1.max(pattern.iter().map(|x| x.len()).max().unwrap_or(1));
+
+        // cells missing from shorter columns stay empty
+        let mut new_pattern = vec![vec![false; width]; height];
+        for (w, col) in pattern.iter().enumerate() {
+            for (h, filled) in col.iter().enumerate() {
+                new_pattern[height - h - 1][w] = *filled;
+            }
+        }
+
+        new_pattern
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Pattern columns separated by `/`, `X` for a filled cell
+    fn pattern(text: &str) -> Vec<Vec<bool>> {
+        text.split('/')
+            .map(|col| col.chars().map(|c| c == 'X').collect())
+            .collect()
+    }
+
+    fn spawn_pattern(shape: usize) -> Vec<Vec<bool>> {
+        TetrisBlock::from_shape(shape, &mut StdRng::seed_from_u64(0), false).pattern
+    }
+
+    #[test]
+    fn rotates_every_shape_through_four_orientations() {
+        let expected = [
+            ["XXXX", "X/X/X/X", "XXXX", "X/X/X/X"],
+            ["XX/XX", "XX/XX", "XX/XX", "XX/XX"],
+            ["XXX/OXO", "XO/XX/XO", "OXO/XXX", "OX/XX/OX"],
+            ["OXX/XXO", "XO/XX/OX", "OXX/XXO", "XO/XX/OX"],
+            ["XXO/OXX", "OX/XX/XO", "XXO/OXX", "OX/XX/XO"],
+            ["XXX/OOX", "XX/XO/XO", "XOO/XXX", "OX/OX/XX"],
+            ["OOX/XXX", "XX/OX/OX", "XXX/XOO", "XO/XO/XX"],
+        ];
+        for (shape, rotations) in expected.iter().enumerate() {
+            let mut current = spawn_pattern(shape);
+            for (turns, text) in rotations.iter().enumerate() {
+                assert_eq!(current, pattern(text), "shape {shape} after {turns} turns");
+                current = TetrisBlock::rotate90(&current);
+            }
+        }
+    }
+
+    #[test]
+    fn four_rotations_return_to_the_spawn_pattern() {
+        for shape in 0..SHAPES {
+            let spawned = spawn_pattern(shape);
+            let mut current = spawned.clone();
+            for _ in 0..4 {
+                current = TetrisBlock::rotate90(&current);
+            }
+            assert_eq!(current, spawned, "shape {shape}");
+        }
+    }
+
+    #[test]
+    fn shapes_are_tagged_with_their_kind() {
+        let names: Vec<String> = (0..SHAPES)
+            .map(|shape| {
+                let block = TetrisBlock::from_shape(shape, &mut StdRng::seed_from_u64(0), true);
+                assert_eq!(block.kind.index(), shape);
+                block.kind.to_string()
+            })
+            .collect();
+        assert_eq!(names.concat(), "IOTSZJL");
+    }
+
+    #[test]
+    fn custom_pieces_are_trimmed_and_limited_in_size() {
+        let piece = PieceDef::new("P", pattern("OOO/OXX/OXX/OXO/OOO"), None).unwrap();
+        assert_eq!(piece.pattern, pattern("XX/XX/XO"));
+
+        assert!(PieceDef::new("empty", pattern("OO/OO"), None).is_err());
+        assert!(PieceDef::new("long", pattern("XXXXXX"), None).is_err());
+        assert!(PieceDef::new("I5", pattern("XXXXX"), None).is_ok());
+    }
+
+    #[test]
+    fn pads_ragged_patterns_with_empty_cells() {
+        let ragged = vec![vec![true], vec![true, true]];
+        assert_eq!(TetrisBlock::rotate90(&ragged), pattern("OX/XX"));
+        assert_eq!(TetrisBlock::rotate90(&[]), pattern("O"));
+    }
+}